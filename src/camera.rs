@@ -1,27 +1,102 @@
 use crate::prelude::*;
 
+// The camera describes which part of the map is visible in a viewport of
+// `width` by `height` tiles. It is clamped to the bounds of the current map;
+// a map smaller than the viewport is centred in it instead.
+//
+// The camera only scrolls once the player leaves a dead zone of
+// `dead_zone_width` by `dead_zone_height` tiles in the middle of the viewport.
+// An empty dead zone keeps the player centred at all times.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Camera {
     pub left_x: i32,
     pub right_x: i32,
     pub top_y: i32,
     pub bottom_y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub dead_zone_width: i32,
+    pub dead_zone_height: i32,
 }
 
 impl Camera {
-    pub fn new(player_position: Point) -> Self {
-        Self {
-            left_x: player_position.x - DISPLAY_WIDTH / 2,
-            right_x: player_position.x + DISPLAY_WIDTH / 2,
-            top_y: player_position.y - DISPLAY_HEIGHT / 2,
-            bottom_y: player_position.y + DISPLAY_HEIGHT / 2,
-        }
+    pub fn new(player_position: Point, width: i32, height: i32, map: &Map) -> Self {
+        let mut camera = Self {
+            left_x: 0,
+            right_x: width,
+            top_y: 0,
+            bottom_y: height,
+            width,
+            height,
+            dead_zone_width: 0,
+            dead_zone_height: 0,
+        };
+        camera.centre_on(player_position, map);
+        camera
+    }
+
+    // Builder-style setter for the size of the dead zone.
+    pub fn with_dead_zone(mut self, width: i32, height: i32) -> Self {
+        self.dead_zone_width = width;
+        self.dead_zone_height = height;
+        self
+    }
+
+    // Put the player in the middle of the viewport, e.g. after moving to a new map.
+    pub fn centre_on(&mut self, player_position: Point, map: &Map) {
+        self.left_x = scroll_axis(player_position.x, self.width, 0, map.width, 0);
+        self.top_y = scroll_axis(player_position.y, self.height, 0, map.height, 0);
+        self.right_x = self.left_x + self.width;
+        self.bottom_y = self.top_y + self.height;
     }
 
     // Update the camera position based on player movement
-    pub fn on_player_move(&mut self, player_position: Point) {
-        self.left_x = player_position.x - DISPLAY_WIDTH / 2;
-        self.right_x = player_position.x + DISPLAY_WIDTH / 2;
-        self.top_y = player_position.y - DISPLAY_HEIGHT / 2;
-        self.bottom_y = player_position.y + DISPLAY_HEIGHT / 2;
+    pub fn on_player_move(&mut self, player_position: Point, map: &Map) {
+        self.left_x = scroll_axis(
+            player_position.x,
+            self.width,
+            self.dead_zone_width,
+            map.width,
+            self.left_x,
+        );
+        self.top_y = scroll_axis(
+            player_position.y,
+            self.height,
+            self.dead_zone_height,
+            map.height,
+            self.top_y,
+        );
+        self.right_x = self.left_x + self.width;
+        self.bottom_y = self.top_y + self.height;
+    }
+
+    // Check whether a map position falls inside the viewport.
+    pub fn in_view(&self, point: Point) -> bool {
+        point.x >= self.left_x
+            && point.x < self.right_x
+            && point.y >= self.top_y
+            && point.y < self.bottom_y
     }
 }
+
+// Work out where the viewport starts along one axis, given the player's position,
+// the viewport and dead zone lengths, the map length and where the viewport starts now.
+fn scroll_axis(player: i32, view: i32, dead_zone: i32, map: i32, start: i32) -> i32 {
+    // A map smaller than the viewport doesn't scroll; it sits in the middle of it
+    if map <= view {
+        return -(view - map) / 2;
+    }
+
+    // Scroll just far enough to bring the player back to the edge of the dead zone
+    let dead_zone_start = start + (view - dead_zone) / 2;
+    let dead_zone_end = dead_zone_start + dead_zone;
+    let start = if player < dead_zone_start {
+        start - (dead_zone_start - player)
+    } else if player > dead_zone_end {
+        start + (player - dead_zone_end)
+    } else {
+        start
+    };
+
+    start.clamp(0, map - view)
+}
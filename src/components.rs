@@ -1,11 +1,14 @@
 // Legion components are usually structs, but can also be enum types such as options.
 // They don’t have to derive any functionality, it’s a good idea to derive `Clone`.
 // This allows you to make a copy of the component if you need it.
+// Components also derive `Serialize` and `Deserialize` so they can be written to a save file,
+// and must be registered in `save::registry()`.
 
 pub use crate::prelude::*;
+use std::collections::HashSet;
 
 // Render component describes how the player appears on the screen
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Render {
     // bracket-lib class that stores both a foreground and background color in a single struct
     pub color: ColorPair,
@@ -13,15 +16,396 @@ pub struct Render {
     pub glyph: FontCharType,
 }
 
-// Player component is a “tag” indicating that an entity with this component is the player
-// Component does not have to contain any fields.
-// An empty component is called a "tag", serving as a flag that a property exists
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Player;
+// Player component indicates that an entity with this component is the player.
+// It also records how deep in the dungeon the player is, starting from 0.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Player {
+    pub map_level: i32,
+}
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Enemy;
 
-// MovingRandomly component is a "tag" for entities that wander aimlessly.
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct MovingRandomly;
+// The states a monster's AI moves between. See the `monster_ai` system for the transitions.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AiState {
+    // Stands still until it notices the player
+    Idle,
+    // Moves aimlessly
+    Wander,
+    // Chases the player, who was last seen at `last_seen`
+    Hunt { last_seen: Point },
+    // Lost sight of the player and heads for where they were last seen, for a few turns
+    Search { last_known: Point, turns_left: i32 },
+    // Badly hurt and running away from the player
+    Flee,
+}
+
+// MonsterAi drives a monster's behaviour through a state machine.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MonsterAi {
+    pub state: AiState,
+}
+
+// Name is used to describe an entity in the message log.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Name(pub String);
+
+// Health tracks the current and maximum hit points of an entity.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Health {
+    pub current: i32,
+    pub max: i32,
+}
+
+// FieldOfView holds the tiles an entity can currently see.
+// `is_dirty` marks it for recalculation after the entity moved.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldOfView {
+    pub visible_tiles: HashSet<Point>,
+    pub radius: i32,
+    pub is_dirty: bool,
+}
+
+impl FieldOfView {
+    pub fn new(radius: i32) -> Self {
+        Self {
+            visible_tiles: HashSet::new(),
+            radius,
+            is_dirty: true,
+        }
+    }
+}
+
+// WantsToMove is a "message" entity: a request for `entity` to step onto `destination`,
+// carried out (or refused) by the movement system.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WantsToMove {
+    pub entity: Entity,
+    pub destination: Point,
+}
+
+// WantsToAttack is a "message" entity: a request for `attacker` to hit `victim`
+// for `damage` points, resolved by the combat system. A hit also applies `inflicts`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WantsToAttack {
+    pub attacker: Entity,
+    pub victim: Entity,
+    pub damage: i32,
+    pub inflicts: Option<StatusEffect>,
+}
+
+// Item is a "tag" for entities that can be picked up.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Item;
+
+// Carried marks an item as being in the inventory of the given entity.
+// Carried items have no `Point`, so they aren't drawn on the map.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Carried(pub Entity);
+
+// RangedWeapon lets its holder hit targets up to `range` tiles away.
+// On an item it is a weapon for the player; on a monster it is an innate attack.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RangedWeapon {
+    pub range: i32,
+    pub damage: i32,
+}
+
+// Thrown is a "tag" for ranged weapons that leave the inventory when used,
+// landing where they hit.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Thrown;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TrapKind {
+    // Wounds whoever steps on it
+    Spike,
+    // Sends whoever steps on it to a random spot on the map
+    Teleport,
+    // Puts every monster on the level on the alert
+    Alarm,
+}
+
+// Trap is sprung by any creature that steps onto its tile.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trap {
+    pub kind: TrapKind,
+}
+
+// Hidden is a "tag" for traps the player hasn't discovered yet.
+// Hidden entities are neither drawn nor described.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hidden;
+
+// TriggerTrap is a "message" entity: `victim` stepped onto `trap`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TriggerTrap {
+    pub trap: Entity,
+    pub victim: Entity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusKind {
+    // Deals a point of damage every turn
+    Poison,
+    // Sends moves off in random directions
+    Confusion,
+    // Grants an extra action every turn
+    Haste,
+}
+
+impl StatusKind {
+    // How the effect is described on the HUD.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "Poisoned",
+            StatusKind::Confusion => "Confused",
+            StatusKind::Haste => "Hasted",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub turns_remaining: i32,
+}
+
+// StatusEffects holds the effects currently active on a creature.
+// `extra_actions` counts the actions haste has granted this turn that are still unused.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+    pub extra_actions: i32,
+}
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    // Add an effect. An effect that is already active lasts for whichever is longer.
+    pub fn add(&mut self, effect: StatusEffect) {
+        match self
+            .effects
+            .iter_mut()
+            .find(|active| active.kind == effect.kind)
+        {
+            Some(active) => {
+                active.turns_remaining = active.turns_remaining.max(effect.turns_remaining)
+            }
+            None => self.effects.push(effect),
+        }
+    }
+}
+
+// Inflicts is a status effect applied by an item: to whoever drinks a potion,
+// or to whoever is hit by a weapon. On a monster, its attacks apply it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Inflicts(pub StatusEffect);
+
+// Potion is a "tag" for items that are drunk to apply their `Inflicts` or `Healing` effect.
+// Thrown, they shatter and splash everyone close to where they land.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Potion;
+
+// Healing restores this much health to whoever drinks (or is splashed by) a potion.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Healing(pub i32);
+
+// Throwing is a "tag" for the item the player is about to throw, while they pick where to.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Throwing;
+
+// Damage is how hard a creature hits in melee.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Damage(pub i32);
+
+// XpValue is the experience the player earns for killing a monster.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct XpValue(pub i32);
+
+// Experience tracks the player's progress towards the next level.
+// Level-ups wait in `pending_level_ups` until the player has picked their reward.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Experience {
+    pub level: i32,
+    pub xp: i32,
+    pub pending_level_ups: i32,
+}
+
+impl Experience {
+    pub fn new() -> Self {
+        Self {
+            level: 1,
+            xp: 0,
+            pending_level_ups: 0,
+        }
+    }
+
+    // The total experience needed to reach the level after `level`.
+    // Each level takes 10 more experience than the one before.
+    pub fn xp_for_next_level(level: i32) -> i32 {
+        5 * level * (level + 1)
+    }
+
+    // Add experience, queueing a level-up for every threshold crossed.
+    pub fn gain(&mut self, xp: i32) {
+        self.xp += xp;
+        while self.xp >= Self::xp_for_next_level(self.level + self.pending_level_ups) {
+            self.pending_level_ups += 1;
+        }
+    }
+}
+
+// The places on a body where equipment is worn. Each holds one item at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Head,
+    Body,
+    Hands,
+    Ring,
+}
+
+// Equippable items can be worn in the given slot.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+// Equipped marks an item as worn by `owner`. Worn items aren't `Carried` as well.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
+// Defense is how much damage a worn item takes off every hit its wearer suffers.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Defense(pub i32);
+
+// How hungry the player is, from worst to best.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum HungerState {
+    Starving,
+    Weak,
+    Hungry,
+    WellFed,
+}
+
+impl HungerState {
+    pub fn name(self) -> &'static str {
+        match self {
+            HungerState::Starving => "Starving",
+            HungerState::Weak => "Weak",
+            HungerState::Hungry => "Hungry",
+            HungerState::WellFed => "Well Fed",
+        }
+    }
+}
+
+// Hunger is the food the player has left. It goes down by one every turn,
+// and once it runs out the player starves.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hunger {
+    pub food: i32,
+}
+
+impl Hunger {
+    // The most food the player can hold; eating more than that is wasted
+    pub const MAX_FOOD: i32 = 500;
+    // At or below these amounts the player is hungry, then weak
+    const HUNGRY_AT: i32 = 150;
+    const WEAK_AT: i32 = 50;
+
+    pub fn new() -> Self {
+        Self {
+            food: Self::MAX_FOOD,
+        }
+    }
+
+    pub fn state(&self) -> HungerState {
+        match self.food {
+            food if food <= 0 => HungerState::Starving,
+            food if food <= Self::WEAK_AT => HungerState::Weak,
+            food if food <= Self::HUNGRY_AT => HungerState::Hungry,
+            _ => HungerState::WellFed,
+        }
+    }
+
+    pub fn eat(&mut self, food: i32) {
+        self.food = (self.food + food).min(Self::MAX_FOOD);
+    }
+}
+
+// Food is an item that is eaten to restore this much `Hunger`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Food(pub i32);
+
+// Gold is money: what a creature carries, or how much lies in a pile on the floor.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gold(pub i32);
+
+// Shopkeeper is a "tag" for the trader who buys and sells items. The items it
+// has for sale are `Carried` by it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Shopkeeper;
+
+// Class is the name of the class the player chose to play.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Class(pub String);
+
+// Mana is spent casting spells and slowly comes back, a point every turn.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Mana {
+    pub current: i32,
+    pub max: i32,
+}
+
+// KnownSpells lists the spells the player can cast, by name, in the order they were learned.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct KnownSpells(pub Vec<String>);
+
+// Teaches marks a book that teaches the named spell to whoever reads it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Teaches(pub String);
+
+// Casting marks the player as aiming the named spell with the targeting cursor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Casting(pub String);
+
+// WantsToCast is a "message" entity: a request for `caster` to cast `spell` at `target`,
+// resolved by the spellcasting system. The spell has already been checked to be castable.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WantsToCast {
+    pub caster: Entity,
+    pub spell: String,
+    pub target: Point,
+}
+
+// LightSource lights up the tiles around an entity, out to `radius`, in its `color`.
+// Torches carry one, and so do some monsters that glow.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LightSource {
+    pub radius: i32,
+    pub color: (u8, u8, u8),
+}
+
+// Boss marks the monster guarding the bottom of the dungeon. Instead of the usual
+// `MonsterAi` it fights in phases, using its special abilities every few turns.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Boss {
+    // Whether the boss has seen the player; it waits for them until then
+    pub engaged: bool,
+    // Whether it has been hurt badly enough to fight harder
+    pub enraged: bool,
+    // Turns until it can use a special ability again
+    pub cooldown: i32,
+    // The tiles its area attack hits next turn, shown to the player as a warning
+    pub telegraphed: Vec<Point>,
+}
+
+// Amulet is a "tag" for the item the player came for. Carrying it wins the game.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Amulet;
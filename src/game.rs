@@ -0,0 +1,450 @@
+use crate::prelude::*;
+
+// The upper bound on schedule runs needed to get back to `TurnState::AwaitingInput`
+// after the player acts, so `step` can never spin forever.
+const MAX_TICKS_PER_STEP: usize = 16;
+// The deepest level of the dungeon, counting from 0. It has no way further down.
+pub const FINAL_DEPTH: i32 = 2;
+
+// The game simulation: the ECS world, its resources and the schedules that update them.
+// It knows nothing about `BTerm`, so it can be driven by the window or by a headless driver.
+pub struct Game {
+    pub ecs: World,
+    pub resources: Resources,
+    input_systems: Schedule,
+    player_systems: Schedule,
+    monster_systems: Schedule,
+    hasted_monster_systems: Schedule,
+}
+
+impl Game {
+    // Start a new run, playing the class called `class`.
+    pub fn new(rng: RandomNumberGenerator, class: &str) -> Self {
+        let mut rng = rng;
+        // legion stores all entities and components in the `World` struct
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        let map_builder = build_level(&mut rng, 0);
+        let templates = Templates::load();
+
+        // Calling spawn_player to add the player and their components to the ECS
+        let class = templates.class(class);
+        spawn_player(&mut ecs, &templates, class, map_builder.player_start);
+        populate(&mut ecs, &mut rng, &templates, &map_builder, 0);
+
+        // Map and camera are part of our resources list
+        resources.insert(
+            Camera::new(
+                map_builder.player_start,
+                DISPLAY_WIDTH,
+                DISPLAY_HEIGHT,
+                &map_builder.map,
+            )
+            .with_dead_zone(DISPLAY_WIDTH / 4, DISPLAY_HEIGHT / 4),
+        );
+        resources.insert(map_builder.map);
+        resources.insert(TurnState::AwaitingInput);
+        let mut identification = Identification::new(&mut rng, &templates.potion_names());
+        if class.knows_potions {
+            templates.potion_names().iter().for_each(|name| {
+                identification.identify(name);
+            });
+        }
+        resources.insert(identification);
+        resources.insert(rng);
+        resources.insert(RunStats::default());
+
+        let mut log = GameLog::default();
+        log.add("Welcome to the dungeon!", YELLOW);
+        resources.insert(log);
+
+        Self::with_world(ecs, resources)
+    }
+
+    pub fn with_world(ecs: World, resources: Resources) -> Self {
+        let mut resources = resources;
+        // Bindings are a player preference rather than game state, so they are not saved.
+        // Front-ends may replace the defaults with `set_key_bindings`.
+        resources.insert(KeyBindings::default());
+        // The occupancy index is rebuilt at the start of every turn, so it isn't saved either
+        resources.insert(Occupancy::default());
+        // Nor is the light, which is worked out from the light sources at the same time
+        resources.insert(Lighting::default());
+        // Templates are part of the game's data rather than its state
+        resources.insert(Templates::load());
+
+        Self {
+            ecs,
+            resources,
+            input_systems: build_input_scheduler(),
+            player_systems: build_player_scheduler(),
+            monster_systems: build_monster_scheduler(),
+            hasted_monster_systems: build_hasted_monster_scheduler(),
+        }
+    }
+
+    pub fn set_key_bindings(&mut self, bindings: KeyBindings) {
+        self.resources.insert(bindings);
+    }
+
+    // The action the current key bindings map `key` to.
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.resources.get::<KeyBindings>()?.action(key)
+    }
+
+    pub fn turn_state(&self) -> TurnState {
+        *self.resources.get::<TurnState>().unwrap()
+    }
+
+    // Run the schedule for the current turn state once, with `key` as the keyboard state.
+    pub fn tick(&mut self, key: Option<VirtualKeyCode>) {
+        // Add the key (which holds the keyboard state) as a resource
+        // to make the current keyboard state available to any system that requests it
+        // When a resource is inserted into Legion’s resource handler, it replaces any existing resource of the same type
+        self.resources.insert(key);
+
+        let schedule = match self.turn_state() {
+            TurnState::AwaitingInput
+            | TurnState::Targeting { .. }
+            | TurnState::Equipment
+            | TurnState::Inventory { .. }
+            | TurnState::Spells
+            | TurnState::LevelUp => &mut self.input_systems,
+            TurnState::PlayerTurn => &mut self.player_systems,
+            TurnState::MonsterTurn => &mut self.monster_systems,
+            TurnState::HastedMonsterTurn => &mut self.hasted_monster_systems,
+            TurnState::NextLevel => {
+                self.advance_level();
+                return;
+            }
+            TurnState::GameOver | TurnState::Victory => return,
+        };
+        schedule.execute(&mut self.ecs, &mut self.resources);
+    }
+
+    // Take the player down the stairs to a freshly built level. The player keeps
+    // everything they carry or wear, and their health and experience; all else is left behind.
+    fn advance_level(&mut self) {
+        let player = <Entity>::query()
+            .filter(component::<Player>())
+            .iter(&self.ecs)
+            .copied()
+            .next()
+            .unwrap();
+
+        let mut carried: Vec<Entity> = <(Entity, &Carried)>::query()
+            .iter(&self.ecs)
+            .filter(|(_, carried)| carried.0 == player)
+            .map(|(entity, _)| *entity)
+            .collect();
+        <(Entity, &Equipped)>::query()
+            .iter(&self.ecs)
+            .filter(|(_, equipped)| equipped.owner == player)
+            .for_each(|(entity, _)| carried.push(*entity));
+        let left_behind: Vec<Entity> = <Entity>::query()
+            .iter(&self.ecs)
+            .filter(|entity| **entity != player && !carried.contains(entity))
+            .copied()
+            .collect();
+        left_behind.iter().for_each(|entity| {
+            self.ecs.remove(*entity);
+        });
+
+        let mut rng = self.resources.remove::<RandomNumberGenerator>().unwrap();
+        let depth = self.player_depth() + 1;
+        let map_builder = build_level(&mut rng, depth);
+
+        if let Some(mut entry) = self.ecs.entry(player) {
+            entry.add_component(Player { map_level: depth });
+            entry.add_component(map_builder.player_start);
+            if let Ok(fov) = entry.get_component_mut::<FieldOfView>() {
+                fov.is_dirty = true;
+            }
+        }
+        let templates = self.resources.get::<Templates>().unwrap().clone();
+        populate(&mut self.ecs, &mut rng, &templates, &map_builder, depth);
+
+        self.resources.insert(
+            Camera::new(
+                map_builder.player_start,
+                DISPLAY_WIDTH,
+                DISPLAY_HEIGHT,
+                &map_builder.map,
+            )
+            .with_dead_zone(DISPLAY_WIDTH / 4, DISPLAY_HEIGHT / 4),
+        );
+        self.resources.insert(map_builder.map);
+        self.resources.insert(rng);
+        self.resources.insert(TurnState::AwaitingInput);
+        if let Some(mut log) = self.resources.get_mut::<GameLog>() {
+            log.add(
+                format!("You descend to depth {} of {}.", depth + 1, FINAL_DEPTH + 1),
+                YELLOW,
+            );
+        }
+    }
+
+    // How deep the player is, starting from 0 at the top of the dungeon.
+    pub fn player_depth(&self) -> i32 {
+        <&Player>::query()
+            .iter(&self.ecs)
+            .next()
+            .map_or(0, |player| player.map_level)
+    }
+
+    // The run as it would be listed in the high-score table, were it to end now.
+    pub fn score_entry(&self) -> ScoreEntry {
+        let (class, xp, gold) = <(&Class, &Experience, &Gold)>::query()
+            .filter(component::<Player>())
+            .iter(&self.ecs)
+            .next()
+            .map_or((String::new(), 0, 0), |(class, experience, gold)| {
+                (class.0.clone(), experience.xp, gold.0)
+            });
+        let victory = self.turn_state() == TurnState::Victory;
+        let depth = self.player_depth() + 1;
+        let stats = self.resources.get::<RunStats>().unwrap();
+
+        ScoreEntry {
+            class,
+            score: ScoreEntry::score(depth, xp, gold, victory),
+            depth,
+            turns: stats.turns,
+            kills: stats.total_kills(),
+            items_used: stats.items_used,
+            fate: if victory {
+                "Won with the Amulet".to_string()
+            } else {
+                stats
+                    .cause_of_death
+                    .clone()
+                    .unwrap_or_else(|| "Still exploring".to_string())
+            },
+        }
+    }
+
+    // Feed a single key press and run the game until it awaits input again.
+    pub fn step(&mut self, key: VirtualKeyCode) {
+        self.tick(Some(key));
+        for _ in 0..MAX_TICKS_PER_STEP {
+            let turn_state = self.turn_state();
+            if turn_state.awaits_input() || turn_state.is_over() {
+                break;
+            }
+            self.tick(None);
+        }
+    }
+
+    pub fn player_position(&self) -> Option<Point> {
+        <&Point>::query()
+            .filter(component::<Player>())
+            .iter(&self.ecs)
+            .next()
+            .copied()
+    }
+
+    // Draw the whole map and its entities as text, one line per map row.
+    pub fn to_ascii(&self) -> String {
+        let map = self.resources.get::<Map>().unwrap();
+        let mut glyphs: Vec<char> = map
+            .tiles
+            .iter()
+            .map(|tile| match tile {
+                TileType::Floor => '.',
+                TileType::Wall => '#',
+                TileType::Door { open: false } => '+',
+                TileType::Door { open: true } => '\'',
+                TileType::Exit => '>',
+            })
+            .collect();
+
+        // Draw traps and items first, so creatures standing on them stay visible.
+        // Unlike the windowed game, hidden traps are shown too.
+        let mut entities: Vec<_> = <(&Point, &Render, Option<&Item>, Option<&Trap>)>::query()
+            .iter(&self.ecs)
+            .collect();
+        entities.sort_by_key(|(_, _, item, trap)| item.is_none() && trap.is_none());
+        entities
+            .into_iter()
+            .map(|(pos, render, _, _)| (pos, render))
+            .filter_map(|(pos, render)| map.try_index(*pos).map(|idx| (idx, render)))
+            .for_each(|(idx, render)| glyphs[idx] = to_char(render.glyph as u8));
+
+        glyphs
+            .chunks(map.width as usize)
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Build the map for the given depth. Every level but the last has a way further down,
+// and the levels between the first and the last have a shop. The last has the boss's lair
+// where the way down would be.
+fn build_level(rng: &mut RandomNumberGenerator, depth: i32) -> MapBuilder {
+    let mut map_builder = MapBuilder::new(rng, SCREEN_WIDTH, SCREEN_HEIGHT);
+    if depth < FINAL_DEPTH {
+        let exit_idx = map_builder
+            .map
+            .map_index(map_builder.exit.x, map_builder.exit.y);
+        map_builder.map.tiles[exit_idx] = TileType::Exit;
+    }
+    if depth > 0 && depth < FINAL_DEPTH {
+        map_builder.build_shop();
+    }
+    if depth == FINAL_DEPTH {
+        map_builder.build_arena();
+    }
+    map_builder
+}
+
+// Fill every room but the player's with a monster suited to the depth,
+// and some of them with items and traps. The shop gets its shopkeeper instead,
+// and the boss has its lair to itself. Torches light some of the rooms.
+fn populate(
+    ecs: &mut World,
+    rng: &mut RandomNumberGenerator,
+    templates: &Templates,
+    map_builder: &MapBuilder,
+    depth: i32,
+) {
+    let rooms: Vec<&Rect> = map_builder
+        .rooms
+        .iter()
+        .skip(1)
+        .filter(|room| {
+            map_builder
+                .shop
+                .is_none_or(|shop| !room.point_in_rect(shop))
+                && map_builder.arena.is_none_or(|arena| !arena.intersect(room))
+        })
+        .collect();
+    if let Some(shop) = map_builder.shop {
+        spawn_shopkeeper(ecs, rng, templates, shop);
+    }
+    if let (Some(arena), Some(boss)) = (map_builder.arena, templates.boss()) {
+        spawn_boss(ecs, rng, boss, arena.center());
+    }
+
+    rooms.iter().for_each(|room| {
+        let template = templates.random_monster(rng, depth);
+        spawn_monster(ecs, rng, template, room.center());
+    });
+
+    // Scatter items through some of those rooms too
+    rooms.iter().for_each(|room| {
+        if rng.range(0, 3) == 0 {
+            let pos = Point::new(rng.range(room.x1, room.x2), rng.range(room.y1, room.y2));
+            spawn_item(ecs, templates.random_item(rng), pos);
+        }
+    });
+
+    // And hide traps in some of them, away from where the monster stands
+    rooms.iter().for_each(|room| {
+        if rng.range(0, 3) == 0 {
+            let pos = Point::new(rng.range(room.x1, room.x2), rng.range(room.y1, room.y2));
+            if pos != room.center() && pos != map_builder.exit {
+                spawn_trap(ecs, rng, pos);
+            }
+        }
+    });
+    // Light some of the rooms, the shop and the boss's lair with torches on the wall
+    // above them. Where a corridor leaves the room there's no wall to hang one on.
+    let mut torches: Vec<Point> = map_builder
+        .rooms
+        .iter()
+        .filter(|_| rng.range(0, 2) == 0)
+        .map(|room| Point::new(room.center().x, room.y1 - 1))
+        .collect();
+    if let Some(arena) = map_builder.arena {
+        torches.push(Point::new(arena.x1 + 2, arena.y1 - 1));
+        torches.push(Point::new(arena.x2 - 3, arena.y1 - 1));
+    }
+    if let Some(shop) = map_builder.shop {
+        torches.push(shop + Point::new(0, -1));
+    }
+    torches
+        .into_iter()
+        .filter(|pos| {
+            map_builder
+                .map
+                .try_index(*pos)
+                .map(|idx| map_builder.map.tiles[idx])
+                == Some(TileType::Wall)
+        })
+        .for_each(|pos| spawn_torch(ecs, pos));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Play `keys` from a new game on `seed`, as the headless driver does.
+    fn play(seed: u64, keys: &[VirtualKeyCode]) -> Game {
+        let mut game = Game::new(RandomNumberGenerator::seeded(seed), "Warrior");
+        keys.iter().for_each(|key| game.step(*key));
+        game
+    }
+
+    #[test]
+    fn a_step_moves_the_player_and_hands_back_control() {
+        let mut game = play(7, &[]);
+        let start = game.player_position().unwrap();
+
+        // Step onto whichever neighbouring tile is open; the first room has no monsters in it
+        let (key, delta) = [
+            (VirtualKeyCode::Right, Point::new(1, 0)),
+            (VirtualKeyCode::Left, Point::new(-1, 0)),
+            (VirtualKeyCode::Down, Point::new(0, 1)),
+            (VirtualKeyCode::Up, Point::new(0, -1)),
+        ]
+        .into_iter()
+        .find(|(_, delta)| {
+            game.resources
+                .get::<Map>()
+                .unwrap()
+                .can_enter_tile(start + *delta)
+        })
+        .unwrap();
+        game.step(key);
+
+        assert_eq!(game.player_position(), Some(start + delta));
+        assert_eq!(game.turn_state(), TurnState::AwaitingInput);
+    }
+
+    #[test]
+    fn the_same_seed_and_keys_play_out_the_same() {
+        use VirtualKeyCode::*;
+        let keys = [Right, Right, Down, Left, Up, Up, Down, Down, Right, Left];
+
+        let (first, second) = (play(42, &keys), play(42, &keys));
+
+        assert_eq!(first.to_ascii(), second.to_ascii());
+        assert_eq!(first.player_position(), second.player_position());
+    }
+
+    #[test]
+    fn the_hasted_monsters_turn_does_not_move_the_clock_on() {
+        let mut game = play(3, &[]);
+        let food = |game: &Game| {
+            <&Hunger>::query()
+                .filter(component::<Player>())
+                .iter(&game.ecs)
+                .next()
+                .unwrap()
+                .food
+        };
+        let before = food(&game);
+        let turns = game.score_entry().turns;
+
+        game.resources.insert(TurnState::HastedMonsterTurn);
+        game.tick(None);
+        assert_eq!(food(&game), before);
+        assert_eq!(game.score_entry().turns, turns);
+
+        game.resources.insert(TurnState::MonsterTurn);
+        game.tick(None);
+        assert_eq!(food(&game), before - 1);
+        assert_eq!(game.score_entry().turns, turns + 1);
+    }
+}
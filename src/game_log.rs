@@ -0,0 +1,37 @@
+use crate::prelude::*;
+
+// A single line of the message log, drawn in its own colour.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub text: String,
+    pub color: RGB,
+}
+
+// Resource collecting what happens in the game, in the order it happened.
+// Systems append to it; the HUD shows the latest entries.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GameLog {
+    entries: Vec<LogEntry>,
+}
+
+impl GameLog {
+    pub fn add<S: ToString>(&mut self, text: S, color: (u8, u8, u8)) {
+        self.entries.push(LogEntry {
+            text: text.to_string(),
+            color: RGB::named(color),
+        });
+    }
+
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+}
+
+// Put "a" or "an" in front of a name, as in "an Alarm Trap".
+pub fn a_or_an(name: &str) -> String {
+    let vowel = name
+        .chars()
+        .next()
+        .is_some_and(|first| "AEIOUaeiou".contains(first));
+    format!("{} {}", if vowel { "an" } else { "a" }, name)
+}
@@ -0,0 +1,41 @@
+use crate::prelude::*;
+use std::fs;
+
+// Run the game without a window: read whitespace-separated key names from `script`,
+// feed them to the game one turn at a time, then print the resulting state.
+// With a fixed `seed` the outcome is reproducible, which makes it usable from CI.
+// The player is of the given `class`, or the first class in the templates.
+pub fn run_headless(script: &str, seed: u64, class: &str) -> BError {
+    let script = fs::read_to_string(script)?;
+    let mut game = Game::new(RandomNumberGenerator::seeded(seed), class);
+
+    for name in script.split_whitespace() {
+        if game.turn_state().is_over() {
+            break;
+        }
+        let key = key_from_name(name).ok_or_else(|| format!("unknown key '{}'", name))?;
+        game.step(key);
+    }
+
+    println!("{}", game.to_ascii());
+    if let Some(pos) = game.player_position() {
+        println!("player: {}, {}", pos.x, pos.y);
+    }
+    println!("entities: {}", game.ecs.len());
+    if let Some(log) = game.resources.get::<GameLog>() {
+        log.entries()
+            .iter()
+            .for_each(|entry| println!("log: {}", entry.text));
+    }
+    match game.turn_state() {
+        TurnState::GameOver => println!("game over"),
+        TurnState::Victory => println!("victory"),
+        _ => {}
+    }
+    let entry = game.score_entry();
+    println!(
+        "score: {} (depth {}, {} turns, {} kills, {} items used, {})",
+        entry.score, entry.depth, entry.turns, entry.kills, entry.items_used, entry.fate
+    );
+    Ok(())
+}
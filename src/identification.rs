@@ -0,0 +1,79 @@
+use crate::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+// What unidentified potions look like. Each run hands these out to the kinds
+// of potion at random, so a "Murky Potion" heals in one game and poisons in the next.
+const POTION_APPEARANCES: [&str; 6] = ["Murky", "Bubbling", "Smoky", "Fizzy", "Glowing", "Cloudy"];
+
+// Resource tracking which items the player can recognise. Items are spawned with their
+// real names; until their kind is identified, they are shown by their appearance instead.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Identification {
+    appearances: BTreeMap<String, String>,
+    known: BTreeSet<String>,
+}
+
+impl Identification {
+    // Give each of the named kinds of potion a random appearance for this run.
+    // Should there be more kinds than appearances, the rest are known by their real names.
+    pub fn new(rng: &mut RandomNumberGenerator, potions: &[&str]) -> Self {
+        let mut appearances: Vec<&str> = POTION_APPEARANCES.to_vec();
+        let appearances = potions
+            .iter()
+            .filter_map(|potion| {
+                if appearances.is_empty() {
+                    return None;
+                }
+                let appearance = appearances.remove(rng.range(0, appearances.len()));
+                Some((potion.to_string(), format!("{} Potion", appearance)))
+            })
+            .collect();
+
+        Self {
+            appearances,
+            known: BTreeSet::new(),
+        }
+    }
+
+    // How the player sees an item called `name`: by its real name once it is
+    // identified (or if it never needed identifying), by its appearance until then.
+    pub fn display_name(&self, name: &str) -> String {
+        match self.appearances.get(name) {
+            Some(appearance) if !self.known.contains(name) => appearance.clone(),
+            _ => name.to_string(),
+        }
+    }
+
+    // Whether the player knows what an item called `name` is.
+    pub fn is_identified(&self, name: &str) -> bool {
+        !self.appearances.contains_key(name) || self.known.contains(name)
+    }
+
+    // Learn what every item called `name` is. Returns whether it was unknown before.
+    pub fn identify(&mut self, name: &str) -> bool {
+        self.appearances.contains_key(name) && self.known.insert(name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn potions_beyond_the_appearances_go_by_their_real_names() {
+        let potions: Vec<String> = (1..=POTION_APPEARANCES.len() + 1)
+            .map(|i| format!("Potion {}", i))
+            .collect();
+        let names: Vec<&str> = potions.iter().map(|name| name.as_str()).collect();
+
+        let identification = Identification::new(&mut RandomNumberGenerator::seeded(3), &names);
+
+        let disguised = names
+            .iter()
+            .filter(|name| identification.display_name(name) != **name)
+            .count();
+        assert_eq!(disguised, POTION_APPEARANCES.len());
+        let last = names[names.len() - 1];
+        assert_eq!(identification.display_name(last), last);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// Players can override the default bindings with this file. It maps action names to
+// lists of key names, e.g. `{ "MoveNorth": ["Up", "W"], "Wait": ["Space"] }`.
+// Actions that are left out keep their default keys. A key may only be bound to one action.
+// The options screen writes it too.
+pub const KEY_BINDINGS_FILE: &str = "keybindings.json";
+
+// Everything the player can ask for from the keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    MoveNorth,
+    MoveSouth,
+    MoveEast,
+    MoveWest,
+    MoveNorthEast,
+    MoveNorthWest,
+    MoveSouthEast,
+    MoveSouthWest,
+    Wait,
+    PickUp,
+    Quaff,
+    Eat,
+    Drop,
+    Throw,
+    Read,
+    Cast,
+    Equipment,
+    Search,
+    CloseDoor,
+    Fire,
+    NextTarget,
+    Confirm,
+    Cancel,
+    ShowLog,
+}
+
+impl Action {
+    // The step taken by a movement action, or `None` for other actions.
+    pub fn delta(self) -> Option<Point> {
+        match self {
+            Action::MoveNorth => Some(Point::new(0, -1)),
+            Action::MoveSouth => Some(Point::new(0, 1)),
+            Action::MoveEast => Some(Point::new(1, 0)),
+            Action::MoveWest => Some(Point::new(-1, 0)),
+            Action::MoveNorthEast => Some(Point::new(1, -1)),
+            Action::MoveNorthWest => Some(Point::new(-1, -1)),
+            Action::MoveSouthEast => Some(Point::new(1, 1)),
+            Action::MoveSouthWest => Some(Point::new(-1, 1)),
+            _ => None,
+        }
+    }
+}
+
+// Arrow keys, WASD, vi-keys (hjkl and yubn for diagonals) and the numpad.
+fn default_keys() -> BTreeMap<Action, Vec<VirtualKeyCode>> {
+    use VirtualKeyCode::*;
+    let mut keys = BTreeMap::new();
+    keys.insert(Action::MoveNorth, vec![Up, W, K, Numpad8]);
+    keys.insert(Action::MoveSouth, vec![Down, S, J, Numpad2]);
+    keys.insert(Action::MoveEast, vec![Right, D, L, Numpad6]);
+    keys.insert(Action::MoveWest, vec![Left, A, H, Numpad4]);
+    keys.insert(Action::MoveNorthEast, vec![U, Numpad9]);
+    keys.insert(Action::MoveNorthWest, vec![Y, Numpad7]);
+    keys.insert(Action::MoveSouthEast, vec![N, Numpad3]);
+    keys.insert(Action::MoveSouthWest, vec![B, Numpad1]);
+    keys.insert(Action::Wait, vec![Space, Period, Numpad5]);
+    keys.insert(Action::PickUp, vec![G, Comma]);
+    keys.insert(Action::Quaff, vec![Q]);
+    keys.insert(Action::Eat, vec![R]);
+    keys.insert(Action::Drop, vec![P]);
+    keys.insert(Action::Throw, vec![T]);
+    keys.insert(Action::Read, vec![V]);
+    keys.insert(Action::Cast, vec![Z]);
+    keys.insert(Action::Equipment, vec![E]);
+    keys.insert(Action::Search, vec![X]);
+    keys.insert(Action::CloseDoor, vec![C]);
+    keys.insert(Action::Fire, vec![F]);
+    keys.insert(Action::NextTarget, vec![Tab]);
+    keys.insert(Action::Confirm, vec![Return, NumpadEnter]);
+    keys.insert(Action::Cancel, vec![Escape]);
+    keys.insert(Action::ShowLog, vec![M]);
+    keys
+}
+
+// Resource translating key presses into actions.
+#[derive(Clone)]
+pub struct KeyBindings {
+    keys: BTreeMap<Action, Vec<VirtualKeyCode>>,
+    actions: HashMap<VirtualKeyCode, Action>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::from_keys(default_keys())
+    }
+}
+
+impl KeyBindings {
+    fn from_keys(keys: BTreeMap<Action, Vec<VirtualKeyCode>>) -> Self {
+        let actions = keys
+            .iter()
+            .flat_map(|(action, keys)| keys.iter().map(move |key| (*key, *action)))
+            .collect();
+        Self { keys, actions }
+    }
+
+    // Load `KEY_BINDINGS_FILE` if there is one, falling back to the defaults.
+    pub fn load() -> Self {
+        if !Path::new(KEY_BINDINGS_FILE).exists() {
+            return Self::default();
+        }
+
+        match Self::from_file(KEY_BINDINGS_FILE) {
+            Ok(bindings) => bindings,
+            Err(err) => {
+                console::log(format!("Could not load {}: {}", KEY_BINDINGS_FILE, err));
+                Self::default()
+            }
+        }
+    }
+
+    fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    // Read bindings in the format of `KEY_BINDINGS_FILE`.
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let overrides: BTreeMap<Action, Vec<String>> = serde_json::from_str(json)?;
+
+        let mut keys = default_keys();
+        for (action, names) in overrides {
+            let action_keys = names
+                .iter()
+                .map(|name| key_from_name(name).ok_or_else(|| format!("unknown key '{}'", name)))
+                .collect::<Result<_, _>>()?;
+            keys.insert(action, action_keys);
+        }
+
+        // A key can only mean one thing, so one bound to two actions is a mistake in the file
+        let mut bound = HashMap::new();
+        for (action, action_keys) in &keys {
+            for key in action_keys {
+                if let Some(other) = bound.insert(*key, *action) {
+                    return Err(format!(
+                        "key '{}' is bound to both {:?} and {:?}",
+                        key_name(*key),
+                        other,
+                        action
+                    )
+                    .into());
+                }
+            }
+        }
+
+        Ok(Self::from_keys(keys))
+    }
+
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.actions.get(&key).copied()
+    }
+
+    // Every action, in a fixed order, with the keys bound to it.
+    pub fn bindings(&self) -> impl Iterator<Item = (Action, &[VirtualKeyCode])> {
+        self.keys
+            .iter()
+            .map(|(action, keys)| (*action, keys.as_slice()))
+    }
+
+    // Bind `key` to `action` alone, replacing the action's other keys. If another action
+    // had `key`, the two swap: it gets the keys `action` had before in its place, so
+    // no action is left without a key.
+    pub fn rebind(&mut self, action: Action, key: VirtualKeyCode) {
+        let mut keys = std::mem::take(&mut self.keys);
+        let old_keys = keys.insert(action, vec![key]).unwrap_or_default();
+        if let Some(other) = self.actions.get(&key).filter(|other| **other != action) {
+            if let Some(other_keys) = keys.get_mut(other) {
+                other_keys.retain(|bound| *bound != key);
+                other_keys.splice(0..0, old_keys);
+            }
+        }
+        *self = Self::from_keys(keys);
+    }
+
+    // Write every binding to `KEY_BINDINGS_FILE`, to be loaded next time.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        fs::write(
+            KEY_BINDINGS_FILE,
+            serde_json::to_string_pretty(&self.names())?,
+        )?;
+        Ok(())
+    }
+
+    // Every binding on a single line, in the format of `KEY_BINDINGS_FILE`.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&self.names())
+    }
+
+    fn names(&self) -> BTreeMap<Action, Vec<String>> {
+        self.keys
+            .iter()
+            .map(|(action, keys)| (*action, keys.iter().map(|key| key_name(*key)).collect()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_replace_the_default_keys_of_their_action() {
+        let bindings = KeyBindings::from_json(r#"{ "Wait": ["O"] }"#).unwrap();
+
+        assert_eq!(bindings.action(VirtualKeyCode::O), Some(Action::Wait));
+        assert_eq!(bindings.action(VirtualKeyCode::Space), None);
+        assert_eq!(bindings.action(VirtualKeyCode::Up), Some(Action::MoveNorth));
+    }
+
+    #[test]
+    fn a_key_bound_to_two_actions_is_rejected() {
+        // W still moves north by default
+        assert!(KeyBindings::from_json(r#"{ "Wait": ["W"] }"#).is_err());
+        assert!(KeyBindings::from_json(r#"{ "MoveNorth": ["Up"], "Wait": ["Up"] }"#).is_err());
+    }
+
+    #[test]
+    fn rebinding_a_key_in_use_swaps_it_with_the_other_action() {
+        let mut bindings = KeyBindings::from_json(r#"{ "Wait": ["O"] }"#).unwrap();
+
+        bindings.rebind(Action::Wait, VirtualKeyCode::Up);
+
+        assert_eq!(bindings.action(VirtualKeyCode::Up), Some(Action::Wait));
+        assert_eq!(bindings.action(VirtualKeyCode::O), Some(Action::MoveNorth));
+        assert_eq!(bindings.action(VirtualKeyCode::W), Some(Action::MoveNorth));
+        // The swapped bindings can be loaded back
+        assert!(KeyBindings::from_json(&bindings.to_json().unwrap()).is_ok());
+    }
+}
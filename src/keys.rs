@@ -0,0 +1,118 @@
+use crate::prelude::*;
+
+// Keys that can be referred to by name in scripts and config files.
+// The name of a key is the name of its `VirtualKeyCode` variant, e.g. "Left" or "Numpad8".
+const NAMED_KEYS: &[VirtualKeyCode] = {
+    use VirtualKeyCode::*;
+    &[
+        Key1,
+        Key2,
+        Key3,
+        Key4,
+        Key5,
+        Key6,
+        Key7,
+        Key8,
+        Key9,
+        Key0,
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        J,
+        K,
+        L,
+        M,
+        N,
+        O,
+        P,
+        Q,
+        R,
+        S,
+        T,
+        U,
+        V,
+        W,
+        X,
+        Y,
+        Z,
+        Escape,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        Insert,
+        Home,
+        Delete,
+        End,
+        PageDown,
+        PageUp,
+        Left,
+        Up,
+        Right,
+        Down,
+        Back,
+        Return,
+        Space,
+        Numpad0,
+        Numpad1,
+        Numpad2,
+        Numpad3,
+        Numpad4,
+        Numpad5,
+        Numpad6,
+        Numpad7,
+        Numpad8,
+        Numpad9,
+        NumpadAdd,
+        NumpadDecimal,
+        NumpadDivide,
+        NumpadEnter,
+        NumpadMultiply,
+        NumpadSubtract,
+        Apostrophe,
+        Backslash,
+        Colon,
+        Comma,
+        Equals,
+        Grave,
+        LBracket,
+        Minus,
+        Period,
+        RBracket,
+        Semicolon,
+        Slash,
+        Tab,
+    ]
+};
+
+// Whether `key` has a name, and so can be written to a script or config file and read back.
+pub fn is_named_key(key: VirtualKeyCode) -> bool {
+    NAMED_KEYS.contains(&key)
+}
+
+// Look up a key by its (case-insensitive) name.
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    NAMED_KEYS
+        .iter()
+        .copied()
+        .find(|key| key_name(*key).eq_ignore_ascii_case(name))
+}
+
+// The name used for a key in scripts and config files.
+pub fn key_name(key: VirtualKeyCode) -> String {
+    format!("{:?}", key)
+}
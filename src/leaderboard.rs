@@ -0,0 +1,148 @@
+use crate::prelude::*;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// The best finished runs, kept between sessions.
+pub const LEADERBOARD_FILE: &str = "leaderboard.json";
+// How many runs the table keeps
+const MAX_ENTRIES: usize = 10;
+
+// What each part of a run is worth
+const SCORE_PER_DEPTH: i32 = 100;
+const SCORE_PER_XP: i32 = 2;
+const SCORE_PER_GOLD: i32 = 1;
+const VICTORY_BONUS: i32 = 1000;
+
+// A finished run, as listed in the high-score table.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub class: String,
+    pub score: i32,
+    // Counting from 1 at the top of the dungeon
+    pub depth: i32,
+    pub turns: i32,
+    pub kills: i32,
+    pub items_used: i32,
+    // What killed the player, or how the run was won
+    pub fate: String,
+}
+
+impl ScoreEntry {
+    // Each level reached, all the experience earned and the gold kept add to the score,
+    // and winning is worth a bonus of its own.
+    pub fn score(depth: i32, xp: i32, gold: i32, victory: bool) -> i32 {
+        depth * SCORE_PER_DEPTH
+            + xp * SCORE_PER_XP
+            + gold * SCORE_PER_GOLD
+            + if victory { VICTORY_BONUS } else { 0 }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Leaderboard {
+    // Best first
+    pub entries: Vec<ScoreEntry>,
+}
+
+impl Leaderboard {
+    // Load `LEADERBOARD_FILE` if there is one, starting an empty table otherwise.
+    pub fn load() -> Self {
+        if !Path::new(LEADERBOARD_FILE).exists() {
+            return Self::default();
+        }
+
+        match Self::from_file(LEADERBOARD_FILE) {
+            Ok(leaderboard) => leaderboard,
+            Err(err) => {
+                console::log(format!("Could not load {}: {}", LEADERBOARD_FILE, err));
+                Self::default()
+            }
+        }
+    }
+
+    fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        self.to_file(LEADERBOARD_FILE)
+    }
+
+    fn to_file(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Put `entry` in its place in the table. Returns where it ended up,
+    // or `None` if it wasn't good enough to make the table.
+    pub fn add(&mut self, entry: ScoreEntry) -> Option<usize> {
+        // Ties go to the older run
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| other.score < entry.score)
+            .unwrap_or(self.entries.len());
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(class: &str, score: i32) -> ScoreEntry {
+        ScoreEntry {
+            class: class.to_string(),
+            score,
+            depth: 2,
+            turns: 150,
+            kills: 7,
+            items_used: 3,
+            fate: "Killed by the Orc".to_string(),
+        }
+    }
+
+    #[test]
+    fn the_score_adds_up_depth_experience_gold_and_victory() {
+        assert_eq!(ScoreEntry::score(3, 10, 25, false), 300 + 20 + 25);
+        assert_eq!(ScoreEntry::score(3, 10, 25, true), 300 + 20 + 25 + 1000);
+    }
+
+    #[test]
+    fn the_table_keeps_only_the_best_runs_best_first() {
+        let mut leaderboard = Leaderboard::default();
+        (0..MAX_ENTRIES as i32).for_each(|i| {
+            leaderboard.add(entry("Warrior", 100 + i * 10));
+        });
+
+        assert_eq!(leaderboard.add(entry("Mage", 5)), None);
+        // Ties go to the older run
+        assert_eq!(leaderboard.add(entry("Rogue", 190)), Some(1));
+        assert_eq!(leaderboard.entries.len(), MAX_ENTRIES);
+        assert_eq!(leaderboard.entries[0].score, 190);
+        assert_eq!(leaderboard.entries[1].class, "Rogue");
+        assert_eq!(leaderboard.entries.last().unwrap().score, 110);
+    }
+
+    #[test]
+    fn a_saved_table_loads_back_as_it_was() {
+        let path = std::env::temp_dir().join("dungeoncrawl_test_050.json");
+        let path = path.to_str().unwrap();
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.add(entry("Warrior", 420));
+        leaderboard.add(entry("Mage", 1337));
+
+        leaderboard.to_file(path).unwrap();
+        let loaded = Leaderboard::from_file(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded, leaderboard);
+    }
+}
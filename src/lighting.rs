@@ -0,0 +1,64 @@
+use crate::prelude::*;
+
+// The light every tile gets even with nothing shining on it
+const AMBIENT: f32 = 0.2;
+// How bright a tile has to be for creatures to see it from afar
+const LIT_THRESHOLD: f32 = 0.35;
+// How much dimmer a light is at the edge of its radius than at its source
+const FALLOFF: f32 = 0.75;
+
+// The light falling on each tile of the map. It is recomputed from the light sources
+// at the start of every turn, so it isn't saved.
+#[derive(Default, PartialEq)]
+pub struct Lighting {
+    width: i32,
+    light: Vec<RGB>,
+}
+
+impl Lighting {
+    // Light up `map` with each source's color, fading with distance from the source.
+    // Light doesn't pass through walls or closed doors, and overlapping lights add up.
+    pub fn compute(map: &Map, sources: &[(Point, LightSource)]) -> Self {
+        let ambient = RGB::from_f32(AMBIENT, AMBIENT, AMBIENT);
+        let mut light = vec![ambient; map.tiles.len()];
+
+        sources.iter().for_each(|(pos, source)| {
+            let color = RGB::from_u8(source.color.0, source.color.1, source.color.2);
+            field_of_view_set(*pos, source.radius, map)
+                .iter()
+                .filter_map(|tile| map.try_index(*tile).map(|idx| (*tile, idx)))
+                .for_each(|(tile, idx)| {
+                    let distance = DistanceAlg::Pythagoras.distance2d(*pos, tile);
+                    let intensity =
+                        (1.0 - FALLOFF * distance / source.radius.max(1) as f32).max(0.0);
+                    let lit = light[idx] + color * intensity;
+                    light[idx] = RGB::from_f32(lit.r.min(1.0), lit.g.min(1.0), lit.b.min(1.0));
+                });
+        });
+
+        Self {
+            width: map.width,
+            light,
+        }
+    }
+
+    // The light on the tile at `pos`. Before the first turn everything is fully lit.
+    pub fn light_at(&self, pos: Point) -> RGB {
+        let idx = (pos.y * self.width + pos.x) as usize;
+        match self.light.get(idx) {
+            Some(light) if pos.x >= 0 && pos.x < self.width => *light,
+            _ => RGB::from_f32(1.0, 1.0, 1.0),
+        }
+    }
+
+    // Whether there is enough light at `pos` to see it by.
+    pub fn is_lit(&self, pos: Point) -> bool {
+        let light = self.light_at(pos);
+        light.r.max(light.g).max(light.b) >= LIT_THRESHOLD
+    }
+
+    // `color` as it looks under the light at `pos`.
+    pub fn shade(&self, color: RGB, pos: Point) -> RGB {
+        color * self.light_at(pos)
+    }
+}
@@ -1,10 +1,26 @@
 mod camera;
 mod components;
+mod game;
+mod game_log;
+mod headless;
+mod identification;
+mod key_bindings;
+mod keys;
+mod leaderboard;
+mod lighting;
 mod map;
 mod map_builder;
+mod menu;
+mod occupancy;
+mod replay;
+mod run_stats;
+mod save;
+mod settings;
 mod spawner;
 mod state;
 mod systems;
+mod templates;
+mod turn_state;
 
 // Use prelude to export common functionality of the crate
 // and external libraries to the rest of the program.
@@ -13,38 +29,82 @@ mod prelude {
     pub use legion::systems::CommandBuffer;
     pub use legion::world::SubWorld;
     pub use legion::*;
+    pub use serde::{Deserialize, Serialize};
+    // Default dimensions used when starting a new game. The map and camera
+    // carry their own sizes, so these are only read when constructing them.
     pub const SCREEN_HEIGHT: i32 = 50;
     pub const SCREEN_WIDTH: i32 = 80;
     pub const DISPLAY_WIDTH: i32 = SCREEN_WIDTH / 2;
     pub const DISPLAY_HEIGHT: i32 = SCREEN_HEIGHT / 2;
+    // The HUD uses an 8x8 font on top of the 32x32 map tiles, so it has four times the cells.
+    pub const HUD_WIDTH: i32 = DISPLAY_WIDTH * 4;
+    pub const HUD_HEIGHT: i32 = DISPLAY_HEIGHT * 4;
     pub use crate::camera::*;
     pub use crate::components::*;
+    pub use crate::game::*;
+    pub use crate::game_log::*;
+    pub use crate::headless::*;
+    pub use crate::identification::*;
+    pub use crate::key_bindings::*;
+    pub use crate::keys::*;
+    pub use crate::leaderboard::*;
+    pub use crate::lighting::*;
     pub use crate::map::*;
     pub use crate::map_builder::*;
+    pub use crate::menu::*;
+    pub use crate::occupancy::*;
+    pub use crate::replay::*;
+    pub use crate::run_stats::*;
+    pub use crate::save::*;
+    pub use crate::settings::*;
     pub use crate::spawner::*;
     pub use crate::state::*;
     pub use crate::systems::*;
+    pub use crate::templates::*;
+    pub use crate::turn_state::*;
 }
 
 use prelude::*;
 
 fn main() -> BError {
+    // `dungeoncrawl --headless <script> [seed] [class]` plays a script of key names without a window
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 2 && args[1] == "--headless" {
+        let seed = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(0);
+        let class = args.get(4).map_or("", |class| class.as_str());
+        return run_headless(&args[2], seed, class);
+    }
+
+    // `dungeoncrawl --replay <file>` plays back a recorded run in the window
+    let state = if args.len() > 2 && args[1] == "--replay" {
+        State::replay(Replay::load(&args[2]).map_err(|err| err.to_string())?)
+    } else {
+        State::new()
+    };
+
+    // The font scale chosen on the options screen sizes the window
+    let tile_size = Settings::load().tile_size(32);
     let ctx = BTermBuilder::new()
         .with_title("Dungeon Crawler")
         // Tracks game speed and informs OS to rest between frames
         .with_fps_cap(30.0)
+        // Advanced input queues window events, so we can save before the window closes
+        .with_advanced_input(true)
         // Use display sizes instead of screen sizes
         .with_dimensions(DISPLAY_WIDTH, DISPLAY_HEIGHT)
-        // The tile dimensions are the size of each char in font file
-        .with_tile_dimensions(32, 32)
+        // The tile dimensions are the size of each char in font file, scaled to taste
+        .with_tile_dimensions(tile_size, tile_size)
         .with_resource_path("resources/")
         .with_font("dungeonfont.png", 32, 32)
+        .with_font("terminal8x8.png", 8, 8)
         .with_simple_console(DISPLAY_WIDTH, DISPLAY_HEIGHT, "dungeonfont.png")
         // Add a second console with no background so transparency shows through it.
         .with_simple_console_no_bg(DISPLAY_WIDTH, DISPLAY_HEIGHT, "dungeonfont.png")
+        // Add a third console for the HUD, using bracket-lib's built-in 8x8 terminal font.
+        .with_simple_console_no_bg(HUD_WIDTH, HUD_HEIGHT, "terminal8x8.png")
         .build()?;
 
-    main_loop(ctx, State::new())
+    main_loop(ctx, state)
 }
 
 // Note on `to_cp437`
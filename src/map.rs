@@ -1,14 +1,33 @@
 use crate::prelude::*;
-const NUM_TILES: usize = (SCREEN_WIDTH * SCREEN_HEIGHT) as usize;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum TileType {
     Floor,
     Wall,
+    // Doors block movement and sight while closed. Bumping into one opens it.
+    Door { open: bool },
+    // Stairs down to the next depth of the dungeon
+    Exit,
 }
 
+impl TileType {
+    // Whether creatures can stand on, see through and shoot through the tile.
+    pub fn is_passable(&self) -> bool {
+        matches!(
+            self,
+            TileType::Floor | TileType::Door { open: true } | TileType::Exit
+        )
+    }
+}
+
+// Map carries its own dimensions so maps of different sizes can coexist.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Map {
+    pub width: i32,
+    pub height: i32,
     pub tiles: Vec<TileType>,
+    // Tiles the player has seen, drawn from memory once they are out of sight
+    pub revealed_tiles: Vec<bool>,
 }
 
 // Map uses row-first indexing, so a 5x3 map would be indexed as:
@@ -19,20 +38,85 @@ pub struct Map {
 // x = index % WIDTH;
 // y = index / WIDTH:
 impl Map {
-    pub fn new() -> Self {
+    pub fn new(width: i32, height: i32) -> Self {
         Self {
-            tiles: vec![TileType::Floor; NUM_TILES],
+            width,
+            height,
+            tiles: vec![TileType::Floor; (width * height) as usize],
+            revealed_tiles: vec![false; (width * height) as usize],
         }
     }
 
     // Check that the player can enter the given point's tile.
     pub fn can_enter_tile(&self, point: Point) -> bool {
-        self.in_bounds(point) && self.tiles[map_index(point.x, point.y)] == TileType::Floor
+        self.in_bounds(point) && self.tiles[self.map_index(point.x, point.y)].is_passable()
+    }
+
+    pub fn is_closed_door(&self, point: Point) -> bool {
+        self.in_bounds(point)
+            && self.tiles[self.map_index(point.x, point.y)] == TileType::Door { open: false }
+    }
+
+    pub fn is_open_door(&self, point: Point) -> bool {
+        self.in_bounds(point)
+            && self.tiles[self.map_index(point.x, point.y)] == TileType::Door { open: true }
+    }
+
+    // Open or close the door at `point`. Tiles that aren't doors are left alone.
+    pub fn set_door(&mut self, point: Point, open: bool) {
+        if let Some(idx) = self.try_index(point) {
+            if let TileType::Door { .. } = self.tiles[idx] {
+                self.tiles[idx] = TileType::Door { open };
+            }
+        }
+    }
+
+    // Check that an entity standing at `from` can step to `destination`.
+    // A diagonal step may not cut the corner of a wall: both of the tiles
+    // it passes between must be enterable as well.
+    pub fn can_move(&self, from: Point, destination: Point) -> bool {
+        self.can_step(from, destination, |pos| self.can_enter_tile(pos))
+    }
+
+    // Like `can_move`, but closed doors count as passable, since they can be
+    // opened by bumping into them.
+    pub fn can_reach(&self, from: Point, destination: Point) -> bool {
+        self.can_step(from, destination, |pos| {
+            self.can_enter_tile(pos) || self.is_closed_door(pos)
+        })
+    }
+
+    fn can_step(&self, from: Point, destination: Point, enterable: impl Fn(Point) -> bool) -> bool {
+        if !enterable(destination) {
+            return false;
+        }
+
+        if from.x != destination.x && from.y != destination.y {
+            enterable(Point::new(destination.x, from.y))
+                && enterable(Point::new(from.x, destination.y))
+        } else {
+            true
+        }
+    }
+
+    // The tiles a projectile passes through on its way from `from` to `to`,
+    // excluding `from`, or `None` if a wall is in the way.
+    pub fn line_of_fire(&self, from: Point, to: Point) -> Option<Vec<Point>> {
+        let line: Vec<Point> = line2d(LineAlg::Bresenham, from, to)
+            .into_iter()
+            .skip_while(|pos| *pos == from)
+            .collect();
+
+        if line.iter().all(|pos| self.can_enter_tile(*pos)) {
+            Some(line)
+        } else {
+            None
+        }
     }
 
     // Check that the given point is within the map boundaries.
     pub fn in_bounds(&self, point: Point) -> bool {
-        point.x >= 0 && point.x < SCREEN_WIDTH && point.y >= 0 && point.y < SCREEN_HEIGHT
+        point.x >= 0 && point.x < self.width && point.y >= 0 && point.y < self.height
     }
 
     // Return the given tile's index coordinates or return a `None` option if
@@ -41,12 +125,88 @@ impl Map {
         if !self.in_bounds(point) {
             None
         } else {
-            Some(map_index(point.x, point.y))
+            Some(self.map_index(point.x, point.y))
         }
     }
+
+    // Map a given coordinate into index number in a row-first indexing schema.
+    pub fn map_index(&self, x: i32, y: i32) -> usize {
+        ((y * self.width) + x) as usize
+    }
 }
 
-// Map a given coordinate into index number in a row-first indexing schema.
-pub fn map_index(x: i32, y: i32) -> usize {
-    ((y * SCREEN_WIDTH) + x) as usize
+// bracket-lib's field of view and path-finding algorithms work on any map
+// that describes its dimensions and which of its tiles block sight.
+impl Algorithm2D for Map {
+    fn dimensions(&self) -> Point {
+        Point::new(self.width, self.height)
+    }
+
+    fn in_bounds(&self, point: Point) -> bool {
+        Map::in_bounds(self, point)
+    }
+}
+
+impl BaseMap for Map {
+    fn is_opaque(&self, idx: usize) -> bool {
+        !self.tiles[idx].is_passable()
+    }
+
+    // Path-finding may step in all eight directions, following the same
+    // corner-cutting rule as movement. Diagonal steps cost a little more,
+    // and so do closed doors, which take a turn to open.
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        let mut exits = SmallVec::new();
+        let location = self.index_to_point2d(idx);
+
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let destination = location + Point::new(dx, dy);
+                if (dx, dy) != (0, 0) && self.can_reach(location, destination) {
+                    let mut cost = if dx != 0 && dy != 0 { 1.4 } else { 1.0 };
+                    if self.is_closed_door(destination) {
+                        cost += 1.0;
+                    }
+                    exits.push((self.point2d_to_index(destination), cost));
+                }
+            }
+        }
+
+        exits
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        DistanceAlg::Pythagoras.distance2d(self.index_to_point2d(idx1), self.index_to_point2d(idx2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_line_of_fire_runs_from_next_to_the_shooter_to_the_target() {
+        let map = Map::new(10, 10);
+
+        let line = map
+            .line_of_fire(Point::new(1, 1), Point::new(4, 1))
+            .unwrap();
+
+        assert_eq!(
+            line,
+            vec![Point::new(2, 1), Point::new(3, 1), Point::new(4, 1)]
+        );
+    }
+
+    #[test]
+    fn a_wall_blocks_the_line_of_fire() {
+        let mut map = Map::new(10, 10);
+        let idx = map.map_index(3, 3);
+        map.tiles[idx] = TileType::Wall;
+
+        assert_eq!(map.line_of_fire(Point::new(1, 1), Point::new(5, 5)), None);
+        assert!(map
+            .line_of_fire(Point::new(1, 1), Point::new(5, 1))
+            .is_some());
+    }
 }
@@ -1,25 +1,54 @@
 use crate::prelude::*;
 
 const NUM_ROOMS: usize = 20;
+// Give up on placing more rooms after this many attempts, so that small maps
+// which cannot fit `NUM_ROOMS` rooms still finish building.
+const MAX_ROOM_ATTEMPTS: usize = 1000;
+
+// The size of the boss's lair on the final level
+const ARENA_WIDTH: i32 = 15;
+const ARENA_HEIGHT: i32 = 9;
+
+// The shop is built from this layout, stamped over the middle of a room: `#` is wall,
+// `+` a closed door, `.` floor and `@` where the shopkeeper stands. The outer ring is
+// floor, so corridors into the room still connect.
+const SHOP_PREFAB: &str = "
+.......
+.#####.
+.#.@.#.
+.##+##.
+.......
+";
 
 pub struct MapBuilder {
     pub map: Map,
     pub rooms: Vec<Rect>, // Rooms will be added to the map
     pub player_start: Point,
+    // The reachable spot furthest from the player's start, where the way down goes
+    pub exit: Point,
+    // Where the shopkeeper stands, on levels with a shop
+    pub shop: Option<Point>,
+    // The boss's lair, on the final level
+    pub arena: Option<Rect>,
 }
 
 impl MapBuilder {
-    pub fn new(rng: &mut RandomNumberGenerator) -> Self {
+    pub fn new(rng: &mut RandomNumberGenerator, width: i32, height: i32) -> Self {
         let mut map_builder = MapBuilder {
-            map: Map::new(),
+            map: Map::new(width, height),
             rooms: Vec::new(),
             player_start: Point::zero(),
+            exit: Point::zero(),
+            shop: None,
+            arena: None,
         };
 
         map_builder.fill(TileType::Wall);
         map_builder.build_random_rooms(rng);
         map_builder.build_corridors(rng);
+        map_builder.build_doors();
         map_builder.player_start = map_builder.rooms[0].center();
+        map_builder.exit = map_builder.find_furthest(map_builder.player_start);
 
         map_builder
     }
@@ -31,7 +60,7 @@ impl MapBuilder {
         use std::cmp::{max, min};
         for y in min(y1, y2)..=max(y1, y2) {
             if let Some(index) = self.map.try_index(Point::new(x, y)) {
-                self.map.tiles[index as usize] = TileType::Floor;
+                self.map.tiles[index] = TileType::Floor;
             }
         }
     }
@@ -43,7 +72,7 @@ impl MapBuilder {
         use std::cmp::{max, min};
         for x in min(x1, x2)..=max(x1, x2) {
             if let Some(index) = self.map.try_index(Point::new(x, y)) {
-                self.map.tiles[index as usize] = TileType::Floor;
+                self.map.tiles[index] = TileType::Floor;
             }
         }
     }
@@ -55,7 +84,7 @@ impl MapBuilder {
         // Sort the rooms by their center point before allocating corridors
         // makes it more likely that corridors will connect adjacent rooms
         // and not snake across the whole map
-        rooms.sort_by(|a, b| a.center().x.cmp(&b.center().x));
+        rooms.sort_by_key(|room| room.center().x);
 
         for (i, room) in rooms.iter().enumerate().skip(1) {
             // Obtain the center point of both rooms
@@ -76,14 +105,128 @@ impl MapBuilder {
         }
     }
 
+    // The reachable tile that takes the most steps to walk to from `start`.
+    fn find_furthest(&self, start: Point) -> Point {
+        let dijkstra_map = DijkstraMap::new(
+            self.map.width,
+            self.map.height,
+            &[self.map.point2d_to_index(start)],
+            &self.map,
+            1024.0,
+        );
+
+        dijkstra_map
+            .map
+            .iter()
+            .enumerate()
+            .filter(|(_, distance)| **distance < f32::MAX)
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .map_or(start, |(idx, _)| self.map.index_to_point2d(idx))
+    }
+
+    // Put a closed door wherever a corridor passes through the wall around a room.
+    // A doorway needs walls on both sides of it, so corridors that run along a room's
+    // edge, or rooms that touch, don't get doors.
+    fn build_doors(&mut self) {
+        let rooms = self.rooms.clone();
+
+        for room in rooms.iter() {
+            // The tiles just outside each side of the room, with the direction
+            // along that side
+            let sides = (room.x1..room.x2)
+                .flat_map(|x| {
+                    [
+                        (Point::new(x, room.y1 - 1), Point::new(1, 0)),
+                        (Point::new(x, room.y2), Point::new(1, 0)),
+                    ]
+                })
+                .chain((room.y1..room.y2).flat_map(|y| {
+                    [
+                        (Point::new(room.x1 - 1, y), Point::new(0, 1)),
+                        (Point::new(room.x2, y), Point::new(0, 1)),
+                    ]
+                }));
+
+            for (pos, along) in sides {
+                let is_wall = |pos: Point| {
+                    self.map
+                        .try_index(pos)
+                        .is_none_or(|idx| self.map.tiles[idx] == TileType::Wall)
+                };
+                if self.map.can_enter_tile(pos) && is_wall(pos - along) && is_wall(pos + along) {
+                    let idx = self.map.map_index(pos.x, pos.y);
+                    self.map.tiles[idx] = TileType::Door { open: false };
+                }
+            }
+        }
+    }
+
+    // Turn a room into a shop, if one is big enough for `SHOP_PREFAB`. The player's
+    // room and the room with the way down are left alone.
+    pub fn build_shop(&mut self) {
+        let lines: Vec<&str> = SHOP_PREFAB.trim().lines().collect();
+        let (width, height) = (lines[0].len() as i32, lines.len() as i32);
+
+        let room = self.rooms.iter().skip(1).find(|room| {
+            room.width() >= width && room.height() >= height && !room.point_in_rect(self.exit)
+        });
+        let room = match room {
+            Some(room) => *room,
+            None => return,
+        };
+
+        let top_left = Point::new(
+            room.x1 + (room.width() - width) / 2,
+            room.y1 + (room.height() - height) / 2,
+        );
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let pos = top_left + Point::new(x as i32, y as i32);
+                let idx = self.map.map_index(pos.x, pos.y);
+                self.map.tiles[idx] = match c {
+                    '#' => TileType::Wall,
+                    '+' => TileType::Door { open: false },
+                    _ => TileType::Floor,
+                };
+                if c == '@' {
+                    self.shop = Some(pos);
+                }
+            }
+        }
+    }
+
+    // Carve a large room for the boss around the spot furthest from the player's start,
+    // which is where the way down would otherwise be. Opening the walls up can only
+    // join more of the map together, so everything stays reachable.
+    // On a map too small for it, the arena shrinks to fit inside the outer wall.
+    pub fn build_arena(&mut self) {
+        let (width, height) = (self.map.width, self.map.height);
+        let arena_width = ARENA_WIDTH.min(width - 2).max(1);
+        let arena_height = ARENA_HEIGHT.min(height - 2).max(1);
+        let x = (self.exit.x - arena_width / 2).clamp(1, (width - arena_width - 1).max(1));
+        let y = (self.exit.y - arena_height / 2).clamp(1, (height - arena_height - 1).max(1));
+        let arena = Rect::with_size(x, y, arena_width, arena_height);
+
+        arena.for_each(|pos| {
+            let idx = self.map.map_index(pos.x, pos.y);
+            self.map.tiles[idx] = TileType::Floor;
+        });
+        self.arena = Some(arena);
+    }
+
     // From a solid map of `TileType::Wall`, carve out random, non-overlapping rooms.
     fn build_random_rooms(&mut self, rng: &mut RandomNumberGenerator) {
+        let (width, height) = (self.map.width, self.map.height);
+        let mut attempts = 0;
+
         // Keep generating random rooms until there are `NUM_ROOMS` rooms
-        while self.rooms.len() < NUM_ROOMS {
+        while self.rooms.len() < NUM_ROOMS && attempts < MAX_ROOM_ATTEMPTS {
+            attempts += 1;
+
             // Create a randomly positioned room of random sizes
             let room = Rect::with_size(
-                rng.range(1, SCREEN_WIDTH - 10),
-                rng.range(1, SCREEN_HEIGHT - 10),
+                rng.range(1, (width - 10).max(2)),
+                rng.range(1, (height - 10).max(2)),
                 rng.range(2, 10),
                 rng.range(2, 10),
             );
@@ -99,8 +242,8 @@ impl MapBuilder {
             // If the newly created room fits the map, carve it out
             if !overlap {
                 room.for_each(|p| {
-                    if p.x > 0 && p.x < SCREEN_WIDTH && p.y > 0 && p.y < SCREEN_HEIGHT {
-                        let index = map_index(p.x, p.y);
+                    if p.x > 0 && p.x < width && p.y > 0 && p.y < height {
+                        let index = self.map.map_index(p.x, p.y);
                         self.map.tiles[index] = TileType::Floor;
                     }
                 });
@@ -116,3 +259,24 @@ impl MapBuilder {
         self.map.tiles.iter_mut().for_each(|t| *t = tile);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_arena_fits_inside_a_map_smaller_than_it() {
+        let mut map_builder = MapBuilder::new(&mut RandomNumberGenerator::seeded(2), 30, 10);
+
+        map_builder.build_arena();
+
+        // The arena is carved out, short of the map's edges
+        let map = &map_builder.map;
+        let arena = map_builder.arena.unwrap();
+        assert!(arena.x1 >= 1 && arena.y1 >= 1);
+        assert!(map.in_bounds(Point::new(arena.x2, arena.y2)));
+        arena.for_each(|pos| {
+            assert!(map.tiles[map.map_index(pos.x, pos.y)] == TileType::Floor);
+        });
+    }
+}
@@ -0,0 +1,207 @@
+use crate::prelude::*;
+
+// Which screen the window is showing. The game itself only runs while `Playing`;
+// the menus are drawn on the HUD console.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunState {
+    MainMenu {
+        selection: usize,
+    },
+    // Picking a class for a new run
+    ChooseClass {
+        selection: usize,
+    },
+    Playing,
+    // The high-score table, opened from the main menu
+    HighScores,
+    // The game is shown, frozen, under the pause menu
+    Paused {
+        selection: usize,
+    },
+    // The options screen returns to the pause menu if it was opened from there,
+    // and waits for a key to bind to the selected action while `rebinding`
+    Options {
+        selection: usize,
+        paused: bool,
+        rebinding: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MainMenuItem {
+    NewGame,
+    Continue,
+    HighScores,
+    Options,
+    Quit,
+}
+
+impl MainMenuItem {
+    // The entries of the main menu. A previous run can only be continued if it was saved.
+    pub fn entries() -> Vec<Self> {
+        let mut entries = vec![MainMenuItem::NewGame];
+        if save_exists() {
+            entries.push(MainMenuItem::Continue);
+        }
+        entries.extend([
+            MainMenuItem::HighScores,
+            MainMenuItem::Options,
+            MainMenuItem::Quit,
+        ]);
+        entries
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MainMenuItem::NewGame => "New Game",
+            MainMenuItem::Continue => "Continue",
+            MainMenuItem::HighScores => "High Scores",
+            MainMenuItem::Options => "Options",
+            MainMenuItem::Quit => "Quit",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PauseMenuItem {
+    Resume,
+    Options,
+    MainMenu,
+    Quit,
+}
+
+impl PauseMenuItem {
+    pub const ALL: [PauseMenuItem; 4] = [
+        PauseMenuItem::Resume,
+        PauseMenuItem::Options,
+        PauseMenuItem::MainMenu,
+        PauseMenuItem::Quit,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PauseMenuItem::Resume => "Resume",
+            PauseMenuItem::Options => "Options",
+            PauseMenuItem::MainMenu => "Save and return to the main menu",
+            PauseMenuItem::Quit => "Save and quit",
+        }
+    }
+}
+
+// What a key press does in a menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+impl MenuInput {
+    // Menus follow the movement, confirm and cancel bindings. The arrow keys, Enter and
+    // Escape always work too, so a bad binding can't lock the player out of the menus.
+    pub fn from_key(key: VirtualKeyCode, bindings: &KeyBindings) -> Option<Self> {
+        match (key, bindings.action(key)) {
+            (VirtualKeyCode::Up, _) | (_, Some(Action::MoveNorth)) => Some(MenuInput::Up),
+            (VirtualKeyCode::Down, _) | (_, Some(Action::MoveSouth)) => Some(MenuInput::Down),
+            (VirtualKeyCode::Left, _) | (_, Some(Action::MoveWest)) => Some(MenuInput::Left),
+            (VirtualKeyCode::Right, _) | (_, Some(Action::MoveEast)) => Some(MenuInput::Right),
+            (VirtualKeyCode::Return, _) | (_, Some(Action::Confirm)) => Some(MenuInput::Select),
+            (VirtualKeyCode::Escape, _) | (_, Some(Action::Cancel)) => Some(MenuInput::Back),
+            _ => None,
+        }
+    }
+}
+
+// Move the highlight up or down a menu of `len` entries, wrapping around at the ends.
+pub fn move_selection(selection: usize, len: usize, input: MenuInput) -> usize {
+    match input {
+        MenuInput::Up => (selection + len - 1) % len,
+        MenuInput::Down => (selection + 1) % len,
+        _ => selection,
+    }
+}
+
+// Draw a boxed menu in the middle of the HUD console, with the selected entry highlighted.
+pub fn draw_menu(ctx: &mut BTerm, title: &str, entries: &[String], selection: usize, help: &str) {
+    ctx.set_active_console(2);
+    let width = entries
+        .iter()
+        .map(|entry| entry.len())
+        .chain([title.len(), help.len()])
+        .max()
+        .unwrap_or(0) as i32
+        + 6;
+    let height = entries.len() as i32 + 6;
+    let (x, y) = ((HUD_WIDTH - width) / 2, (HUD_HEIGHT - height) / 2);
+
+    ctx.draw_box_double(x, y, width, height, WHITE, BLACK);
+    ctx.print_color_centered(y + 1, YELLOW, BLACK, title);
+    entries.iter().enumerate().for_each(|(i, entry)| {
+        let (fg, bg) = if i == selection {
+            (BLACK, WHITE)
+        } else {
+            (WHITE, BLACK)
+        };
+        ctx.print_color(x + 3, y + 3 + i as i32, fg, bg, entry);
+    });
+    ctx.print_color_centered(y + height - 2, GREY, BLACK, help);
+}
+
+// Draw the high-score table on the HUD console, its header on row `y`, with the entry
+// at `highlight` picked out.
+pub fn draw_high_scores(
+    ctx: &mut BTerm,
+    leaderboard: &Leaderboard,
+    y: i32,
+    highlight: Option<usize>,
+) {
+    ctx.set_active_console(2);
+    ctx.print_color_centered(y, YELLOW, BLACK, "High Scores");
+    if leaderboard.entries.is_empty() {
+        ctx.print_color_centered(y + 2, GREY, BLACK, "No runs have been finished yet.");
+        return;
+    }
+
+    let rows: Vec<String> = leaderboard
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            format!(
+                "{:>2}. {:>6}  {:<8} depth {}  {:>5} turns  {:>3} kills  {}",
+                i + 1,
+                entry.score,
+                entry.class,
+                entry.depth,
+                entry.turns,
+                entry.kills,
+                entry.fate
+            )
+        })
+        .collect();
+    // The rows are left-aligned with each other, and centred as a block
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0) as i32;
+    let x = (HUD_WIDTH - width) / 2;
+    rows.iter().enumerate().for_each(|(i, row)| {
+        let color = if Some(i) == highlight { GOLD } else { WHITE };
+        ctx.print_color(x, y + 2 + i as i32, color, BLACK, row);
+    });
+}
+
+// A readable name for an action, e.g. "Move north east" for `MoveNorthEast`.
+pub fn action_label(action: Action) -> String {
+    let name = format!("{:?}", action);
+    let mut label = String::new();
+    name.chars().enumerate().for_each(|(i, c)| {
+        if i > 0 && c.is_uppercase() {
+            label.push(' ');
+            label.extend(c.to_lowercase());
+        } else {
+            label.push(c);
+        }
+    });
+    label
+}
@@ -0,0 +1,39 @@
+use crate::prelude::*;
+use std::collections::HashMap;
+
+// Spatial index of the creatures standing on each tile. It is rebuilt at the start
+// of every turn and kept up to date by the movement and combat systems, so two
+// creatures can never end up on the same tile.
+#[derive(Default)]
+pub struct Occupancy {
+    tiles: HashMap<Point, Vec<Entity>>,
+}
+
+impl Occupancy {
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    pub fn add(&mut self, pos: Point, entity: Entity) {
+        self.tiles.entry(pos).or_default().push(entity);
+    }
+
+    pub fn remove(&mut self, pos: Point, entity: Entity) {
+        if let Some(entities) = self.tiles.get_mut(&pos) {
+            entities.retain(|e| *e != entity);
+            if entities.is_empty() {
+                self.tiles.remove(&pos);
+            }
+        }
+    }
+
+    pub fn entities_at(&self, pos: Point) -> &[Entity] {
+        self.tiles
+            .get(&pos)
+            .map_or(&[], |entities| entities.as_slice())
+    }
+
+    pub fn is_occupied(&self, pos: Point) -> bool {
+        self.tiles.contains_key(&pos)
+    }
+}
@@ -0,0 +1,148 @@
+use crate::prelude::*;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Write};
+
+// Every new game started in a window is recorded here, so a tester can send it in.
+pub const REPLAY_FILE: &str = "last_run.replay";
+const REPLAY_HEADER: &str = "dungeoncrawl replay 3";
+const BINDINGS_PREFIX: &str = "bindings ";
+
+// A replay file is plain text: the header, the seed of the random number generator,
+// the player's class and the key bindings in use, then the name of every key fed to the
+// game while it awaited input, one per line. Keys mean what the bindings said at the time,
+// so if the bindings change during the run, the new bindings are written where they took
+// effect.
+//
+//     dungeoncrawl replay 3
+//     seed 1234
+//     class Rogue
+//     bindings {"MoveNorth":["Up","W","K","Numpad8"],...}
+//     Left
+//     Up
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    pub fn create(
+        path: &str,
+        seed: u64,
+        class: &str,
+        bindings: &KeyBindings,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", REPLAY_HEADER)?;
+        writeln!(file, "seed {}", seed)?;
+        writeln!(file, "class {}", class)?;
+        let mut recorder = Self { file };
+        recorder.record_bindings(bindings)?;
+        Ok(recorder)
+    }
+
+    // Keys are written straight to disk so the recording survives a crash.
+    pub fn record(&mut self, key: VirtualKeyCode) -> io::Result<()> {
+        writeln!(self.file, "{}", key_name(key))
+    }
+
+    pub fn record_bindings(&mut self, bindings: &KeyBindings) -> Result<(), Box<dyn Error>> {
+        writeln!(self.file, "{}{}", BINDINGS_PREFIX, bindings.to_json()?)?;
+        Ok(())
+    }
+}
+
+// What a replay feeds to the game next.
+pub enum ReplayStep {
+    Key(VirtualKeyCode),
+    // The keys that follow are to be read with these bindings
+    Bindings(KeyBindings),
+}
+
+pub struct Replay {
+    pub seed: u64,
+    pub class: String,
+    steps: VecDeque<ReplayStep>,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+
+        if lines.next() != Some(REPLAY_HEADER) {
+            return Err(format!("{} is not a replay file", path).into());
+        }
+
+        let seed = lines
+            .next()
+            .and_then(|line| line.strip_prefix("seed "))
+            .ok_or("replay file has no seed")?
+            .parse()?;
+        let class = lines
+            .next()
+            .and_then(|line| line.strip_prefix("class "))
+            .ok_or("replay file has no class")?
+            .to_string();
+
+        let mut lines = lines.peekable();
+        if !lines
+            .peek()
+            .is_some_and(|line| line.starts_with(BINDINGS_PREFIX))
+        {
+            return Err("replay file has no key bindings".into());
+        }
+        let steps = lines
+            .map(|line| match line.strip_prefix(BINDINGS_PREFIX) {
+                Some(json) => Ok(ReplayStep::Bindings(KeyBindings::from_json(json)?)),
+                None => key_from_name(line)
+                    .map(ReplayStep::Key)
+                    .ok_or_else(|| format!("unknown key '{}'", line).into()),
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+
+        Ok(Self { seed, class, steps })
+    }
+
+    // The next recorded step, or `None` once the replay has been played back.
+    pub fn next_step(&mut self) -> Option<ReplayStep> {
+        self.steps.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_recording_plays_back_with_the_bindings_it_was_made_with() {
+        let path = std::env::temp_dir().join("dungeoncrawl_test_030.replay");
+        let path = path.to_str().unwrap();
+
+        let mut recorder = Recorder::create(path, 99, "Rogue", &KeyBindings::default()).unwrap();
+        recorder.record(VirtualKeyCode::Up).unwrap();
+        let moved = KeyBindings::from_json(r#"{ "MoveNorth": ["I"] }"#).unwrap();
+        recorder.record_bindings(&moved).unwrap();
+        recorder.record(VirtualKeyCode::I).unwrap();
+        recorder.record(VirtualKeyCode::Up).unwrap();
+        drop(recorder);
+
+        let mut replay = Replay::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(replay.seed, 99);
+        assert_eq!(replay.class, "Rogue");
+
+        let mut actions = Vec::new();
+        let mut current = None;
+        while let Some(step) = replay.next_step() {
+            match step {
+                ReplayStep::Bindings(bindings) => current = Some(bindings),
+                ReplayStep::Key(key) => actions.push(current.as_ref().unwrap().action(key)),
+            }
+        }
+        assert_eq!(
+            actions,
+            vec![Some(Action::MoveNorth), Some(Action::MoveNorth), None]
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use crate::prelude::*;
+use std::collections::BTreeMap;
+
+// Resource keeping track of how the run has gone, for the game-over screen and the
+// high-score table. Systems update it as things happen; it is saved with the run.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunStats {
+    // How many turns the run has lasted
+    pub turns: i32,
+    // How many of each kind of monster the player has killed, by name
+    pub kills: BTreeMap<String, i32>,
+    // How many potions, meals and books the player has used up
+    pub items_used: i32,
+    // What killed the player, once something has
+    pub cause_of_death: Option<String>,
+}
+
+impl RunStats {
+    pub fn add_kill(&mut self, name: &str) {
+        *self.kills.entry(name.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn total_kills(&self) -> i32 {
+        self.kills.values().sum()
+    }
+
+    // Only the first cause counts: whatever hits a dead player afterwards didn't kill them
+    pub fn died<S: ToString>(&mut self, cause: S) {
+        if self.cause_of_death.is_none() {
+            self.cause_of_death = Some(cause.to_string());
+        }
+    }
+}
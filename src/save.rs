@@ -0,0 +1,155 @@
+use crate::prelude::*;
+use serde::de::DeserializeSeed;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+// Bump this whenever the layout of `SaveGame` or of a saved component changes,
+// so that old save files are rejected instead of being misread.
+pub const SAVE_VERSION: u32 = 20;
+pub const SAVE_FILE: &str = "savegame.json";
+
+// Everything needed to resume a run: the serialized Legion world plus the resources
+// that live outside of it.
+#[derive(Serialize, Deserialize)]
+struct SaveGame {
+    version: u32,
+    world: serde_json::Value,
+    map: Map,
+    camera: Camera,
+    turn_state: TurnState,
+    rng: RandomNumberGenerator,
+    log: GameLog,
+    identification: Identification,
+    stats: RunStats,
+}
+
+// Legion needs to know every component type it may find in a saved world,
+// together with a stable name to identify it in the file.
+fn registry() -> Registry<String> {
+    let mut registry = Registry::<String>::default();
+    registry.register::<Point>("point".to_string());
+    registry.register::<Render>("render".to_string());
+    registry.register::<Player>("player".to_string());
+    registry.register::<Enemy>("enemy".to_string());
+    registry.register::<MonsterAi>("monster_ai".to_string());
+    registry.register::<Name>("name".to_string());
+    registry.register::<Health>("health".to_string());
+    registry.register::<FieldOfView>("field_of_view".to_string());
+    registry.register::<WantsToMove>("wants_to_move".to_string());
+    registry.register::<WantsToAttack>("wants_to_attack".to_string());
+    registry.register::<Item>("item".to_string());
+    registry.register::<Carried>("carried".to_string());
+    registry.register::<RangedWeapon>("ranged_weapon".to_string());
+    registry.register::<Thrown>("thrown".to_string());
+    registry.register::<Trap>("trap".to_string());
+    registry.register::<Hidden>("hidden".to_string());
+    registry.register::<TriggerTrap>("trigger_trap".to_string());
+    registry.register::<StatusEffects>("status_effects".to_string());
+    registry.register::<Inflicts>("inflicts".to_string());
+    registry.register::<Potion>("potion".to_string());
+    registry.register::<Healing>("healing".to_string());
+    registry.register::<Throwing>("throwing".to_string());
+    registry.register::<Damage>("damage".to_string());
+    registry.register::<XpValue>("xp_value".to_string());
+    registry.register::<Experience>("experience".to_string());
+    registry.register::<Equippable>("equippable".to_string());
+    registry.register::<Equipped>("equipped".to_string());
+    registry.register::<Defense>("defense".to_string());
+    registry.register::<Hunger>("hunger".to_string());
+    registry.register::<Food>("food".to_string());
+    registry.register::<Gold>("gold".to_string());
+    registry.register::<Shopkeeper>("shopkeeper".to_string());
+    registry.register::<Boss>("boss".to_string());
+    registry.register::<Amulet>("amulet".to_string());
+    registry.register::<LightSource>("light_source".to_string());
+    registry.register::<Class>("class".to_string());
+    registry.register::<Mana>("mana".to_string());
+    registry.register::<KnownSpells>("known_spells".to_string());
+    registry.register::<Teaches>("teaches".to_string());
+    registry.register::<Casting>("casting".to_string());
+    registry.register::<WantsToCast>("wants_to_cast".to_string());
+    registry
+}
+
+pub fn save_exists() -> bool {
+    Path::new(SAVE_FILE).exists()
+}
+
+// Death is permanent: a finished run can't be resumed.
+pub fn delete_save() {
+    if save_exists() {
+        if let Err(err) = std::fs::remove_file(SAVE_FILE) {
+            console::log(format!("Could not delete {}: {}", SAVE_FILE, err));
+        }
+    }
+}
+
+// Write the world and the game's resources to `SAVE_FILE`.
+pub fn save_game(ecs: &World, resources: &Resources) -> Result<(), Box<dyn Error>> {
+    let registry = registry();
+    let world = serde_json::to_value(ecs.as_serializable(any(), &registry))?;
+
+    let save = SaveGame {
+        version: SAVE_VERSION,
+        world,
+        map: resources.get::<Map>().ok_or("missing map")?.clone(),
+        camera: resources.get::<Camera>().ok_or("missing camera")?.clone(),
+        turn_state: *resources.get::<TurnState>().ok_or("missing turn state")?,
+        rng: resources
+            .get::<RandomNumberGenerator>()
+            .ok_or("missing random number generator")?
+            .clone(),
+        log: resources
+            .get::<GameLog>()
+            .ok_or("missing game log")?
+            .clone(),
+        identification: resources
+            .get::<Identification>()
+            .ok_or("missing identification")?
+            .clone(),
+        stats: resources
+            .get::<RunStats>()
+            .ok_or("missing run stats")?
+            .clone(),
+    };
+
+    let writer = BufWriter::new(File::create(SAVE_FILE)?);
+    serde_json::to_writer(writer, &save)?;
+    Ok(())
+}
+
+// Read `SAVE_FILE` back into a new world and set of resources.
+pub fn load_game() -> Result<(World, Resources), Box<dyn Error>> {
+    let reader = BufReader::new(File::open(SAVE_FILE)?);
+    let value: serde_json::Value = serde_json::from_reader(reader)?;
+
+    // Check the version before anything else, as older layouts may not parse at all
+    let version = value["version"]
+        .as_u64()
+        .ok_or("save file has no version")?;
+    if version != SAVE_VERSION as u64 {
+        return Err(format!(
+            "save file version {} is not supported (expected {})",
+            version, SAVE_VERSION
+        )
+        .into());
+    }
+
+    let save: SaveGame = serde_json::from_value(value)?;
+
+    let registry = registry();
+    let ecs = registry.as_deserialize().deserialize(save.world)?;
+
+    let mut resources = Resources::default();
+    resources.insert(save.map);
+    resources.insert(save.camera);
+    resources.insert(save.turn_state);
+    resources.insert(save.rng);
+    resources.insert(save.log);
+    resources.insert(save.identification);
+    resources.insert(save.stats);
+
+    Ok((ecs, resources))
+}
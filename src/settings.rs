@@ -0,0 +1,64 @@
+use crate::prelude::*;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// Player preferences other than the key bindings, changed on the options screen.
+pub const SETTINGS_FILE: &str = "settings.json";
+
+// The font scales the options screen cycles through
+pub const FONT_SCALES: &[f32] = &[0.5, 0.75, 1.0, 1.5, 2.0];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    // How large the window's tiles are drawn, relative to the font's own size.
+    // The window is built with it, so a change takes effect on the next start.
+    pub font_scale: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self { font_scale: 1.0 }
+    }
+}
+
+impl Settings {
+    // Load `SETTINGS_FILE` if there is one, falling back to the defaults.
+    pub fn load() -> Self {
+        if !Path::new(SETTINGS_FILE).exists() {
+            return Self::default();
+        }
+
+        match Self::from_file(SETTINGS_FILE) {
+            Ok(settings) => settings,
+            Err(err) => {
+                console::log(format!("Could not load {}: {}", SETTINGS_FILE, err));
+                Self::default()
+            }
+        }
+    }
+
+    fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        fs::write(SETTINGS_FILE, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Move to the next larger (`step` 1) or smaller (`step` -1) font scale.
+    pub fn step_font_scale(&mut self, step: i32) {
+        let current = FONT_SCALES
+            .iter()
+            .position(|scale| *scale >= self.font_scale)
+            .unwrap_or(FONT_SCALES.len() - 1) as i32;
+        let next = (current + step).clamp(0, FONT_SCALES.len() as i32 - 1);
+        self.font_scale = FONT_SCALES[next as usize];
+    }
+
+    // The size in pixels of the window's tiles, for fonts drawn `size` pixels across.
+    pub fn tile_size(&self, size: i32) -> i32 {
+        ((size as f32 * self.font_scale) as i32).max(1)
+    }
+}
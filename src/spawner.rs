@@ -1,34 +1,272 @@
 use crate::prelude::*;
 
-// Spawn a player in the given World at the given position
-pub fn spawn_player(ecs: &mut World, pos: Point) {
+// How many items a shopkeeper has for sale
+const SHOP_STOCK: usize = 6;
+// The warm light of a burning torch
+const TORCH_COLOR: (u8, u8, u8) = (255, 200, 120);
+
+// Spawn a player of the given class in the given World at the given position,
+// with the class's starting kit
+pub fn spawn_player(ecs: &mut World, templates: &Templates, class: &ClassTemplate, pos: Point) {
     // We create components by calling `push`.
     // Calling push() creates a new Entity composed of the listed components.
     // The components are separated in a tuple: Entity => (Tag, ..., )
-    ecs.push((
-        Player,
+    let player = ecs.push((
+        Player { map_level: 0 },
         pos,
         Render {
             color: ColorPair::new(WHITE, BLACK),
             glyph: to_cp437('@'),
         },
+        Health {
+            current: class.health,
+            max: class.health,
+        },
+        FieldOfView::new(class.sight.unwrap_or(8)),
+        StatusEffects::default(),
+        Damage(class.damage),
+        Experience::new(),
     ));
+
+    // Legion builds entities from tuples of at most eight components
+    if let Some(mut entry) = ecs.entry(player) {
+        entry.add_component(Hunger::new());
+        entry.add_component(Gold(class.gold));
+        entry.add_component(Class(class.name.clone()));
+        entry.add_component(Mana {
+            current: class.mana,
+            max: class.mana,
+        });
+        entry.add_component(KnownSpells(class.spells.clone()));
+        // The player carries a torch of their own
+        entry.add_component(LightSource {
+            radius: 6,
+            color: TORCH_COLOR,
+        });
+    }
+
+    // Armour is put on, one piece per slot; everything else goes in the pack
+    let mut worn = Vec::new();
+    class
+        .items
+        .iter()
+        .filter_map(|name| templates.item(name))
+        .for_each(|template| {
+            let item = spawn_item(ecs, template, pos);
+            if let Some(mut entry) = ecs.entry(item) {
+                entry.remove_component::<Point>();
+                match template.slot {
+                    Some(slot) if !worn.contains(&slot) => {
+                        worn.push(slot);
+                        entry.add_component(Equipped {
+                            owner: player,
+                            slot,
+                        });
+                    }
+                    _ => entry.add_component(Carried(player)),
+                }
+            }
+        });
 }
 
-// Spawn a monster in the given world at the given position
-pub fn spawn_monster(ecs: &mut World, rng: &mut RandomNumberGenerator, pos: Point) {
-    ecs.push((
+// Spawn a monster in the given world at the given position, as described by its template
+pub fn spawn_monster(
+    ecs: &mut World,
+    rng: &mut RandomNumberGenerator,
+    template: &MonsterTemplate,
+    pos: Point,
+) -> Entity {
+    let monster = ecs.push((
         Enemy,
         pos,
         Render {
-            color: ColorPair::new(WHITE, BLACK),
-            glyph: match rng.range(0, 4) {
-                0 => to_cp437('E'),
-                1 => to_cp437('O'),
-                2 => to_cp437('o'),
-                _ => to_cp437('g'),
+            color: ColorPair::new(template.color, BLACK),
+            glyph: to_cp437(template.glyph),
+        },
+        // Some monsters start out asleep, the others roam the dungeon
+        MonsterAi {
+            state: if rng.range(0, 3) == 0 {
+                AiState::Idle
+            } else {
+                AiState::Wander
+            },
+        },
+        Health {
+            current: template.health,
+            max: template.health,
+        },
+        Name(template.name.clone()),
+        FieldOfView::new(6),
+    ));
+
+    if let Some(mut entry) = ecs.entry(monster) {
+        entry.add_component(StatusEffects::default());
+        entry.add_component(Damage(template.damage));
+        entry.add_component(XpValue(template.xp));
+        // Whatever gold the monster carries is dropped when it dies
+        entry.add_component(Gold(rng.range(0, template.gold + 1)));
+        // Archers shoot the player from a distance
+        if let Some(ranged) = template.ranged {
+            entry.add_component(ranged);
+        }
+        // Some bites are poisonous
+        if let Some(effect) = template.inflicts {
+            entry.add_component(Inflicts(effect));
+        }
+        // Others glow in the dark
+        if let Some(light) = template.light {
+            entry.add_component(light);
+        }
+    }
+    monster
+}
+
+// Spawn the boss in the given world at the given position. It is a monster like any
+// other, except that it follows its own `Boss` tactics instead of a `MonsterAi`.
+pub fn spawn_boss(
+    ecs: &mut World,
+    rng: &mut RandomNumberGenerator,
+    template: &MonsterTemplate,
+    pos: Point,
+) {
+    let boss = spawn_monster(ecs, rng, template, pos);
+    if let Some(mut entry) = ecs.entry(boss) {
+        entry.remove_component::<MonsterAi>();
+        entry.add_component(Boss::default());
+    }
+}
+
+// Spawn an item in the given world at the given position, as described by its template
+pub fn spawn_item(ecs: &mut World, template: &ItemTemplate, pos: Point) -> Entity {
+    let item = ecs.push((
+        Item,
+        pos,
+        Render {
+            color: ColorPair::new(template.color, BLACK),
+            glyph: to_cp437(template.glyph),
+        },
+        Name(template.name.clone()),
+    ));
+
+    if let Some(mut entry) = ecs.entry(item) {
+        if let Some(ranged) = template.ranged {
+            entry.add_component(ranged);
+        }
+        if template.thrown {
+            entry.add_component(Thrown);
+        }
+        if let Some(effect) = template.inflicts {
+            entry.add_component(Inflicts(effect));
+        }
+        if template.potion {
+            entry.add_component(Potion);
+        }
+        if let Some(healing) = template.healing {
+            entry.add_component(Healing(healing));
+        }
+        if let Some(food) = template.food {
+            entry.add_component(Food(food));
+        }
+        if let Some(slot) = template.slot {
+            entry.add_component(Equippable { slot });
+        }
+        if let Some(defense) = template.defense {
+            entry.add_component(Defense(defense));
+        }
+        if let Some(spell) = &template.teaches {
+            entry.add_component(Teaches(spell.clone()));
+        }
+    }
+    item
+}
+
+// Spawn a shopkeeper in the given world at the given position, with a few random
+// items for sale
+pub fn spawn_shopkeeper(
+    ecs: &mut World,
+    rng: &mut RandomNumberGenerator,
+    templates: &Templates,
+    pos: Point,
+) {
+    let shopkeeper = ecs.push((
+        Shopkeeper,
+        pos,
+        Render {
+            color: ColorPair::new(GOLD, BLACK),
+            glyph: to_cp437('@'),
+        },
+        Name("Shopkeeper".to_string()),
+    ));
+
+    for _ in 0..SHOP_STOCK {
+        let item = spawn_item(ecs, templates.random_item(rng), pos);
+        if let Some(mut entry) = ecs.entry(item) {
+            entry.remove_component::<Point>();
+            entry.add_component(Carried(shopkeeper));
+        }
+    }
+}
+
+// Leave what a dead monster carried at the given position: a pile of gold, which
+// is picked up by walking over it, and the Amulet if the monster was the boss.
+pub fn spawn_loot(commands: &mut CommandBuffer, pos: Point, gold: i32, boss: bool) {
+    if gold > 0 {
+        commands.push((
+            pos,
+            Render {
+                color: ColorPair::new(GOLD, BLACK),
+                glyph: to_cp437('$'),
+            },
+            Name(format!("{} Gold", gold)),
+            Gold(gold),
+        ));
+    }
+    if boss {
+        commands.push((
+            Item,
+            Amulet,
+            pos,
+            Render {
+                color: ColorPair::new(WHITE, BLACK),
+                glyph: to_cp437('|'),
             },
+            Name("Amulet of the Deep".to_string()),
+        ));
+    }
+}
+
+// Spawn a torch mounted on the wall at the given position
+pub fn spawn_torch(ecs: &mut World, pos: Point) {
+    ecs.push((
+        pos,
+        Render {
+            color: ColorPair::new(WHITE, BLACK),
+            glyph: to_cp437('/'),
+        },
+        Name("Torch".to_string()),
+        LightSource {
+            radius: 8,
+            color: TORCH_COLOR,
+        },
+    ));
+}
+
+// Spawn a random, hidden trap in the given world at the given position
+pub fn spawn_trap(ecs: &mut World, rng: &mut RandomNumberGenerator, pos: Point) {
+    let (kind, color, name) = match rng.range(0, 3) {
+        0 => (TrapKind::Teleport, MAGENTA, "Teleport Trap"),
+        1 => (TrapKind::Alarm, YELLOW, "Alarm Trap"),
+        _ => (TrapKind::Spike, RED, "Spike Trap"),
+    };
+
+    ecs.push((
+        Trap { kind },
+        Hidden,
+        pos,
+        Render {
+            color: ColorPair::new(color, BLACK),
+            glyph: to_cp437('^'),
         },
-        MovingRandomly {},
+        Name(name.to_string()),
     ));
 }
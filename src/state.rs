@@ -1,44 +1,601 @@
 pub use crate::prelude::*;
 
+// The windowed front-end: shows the menus, and owns the game simulation and draws it
+// to a `BTerm` once one is started.
 pub struct State {
-    ecs: World,
-    resources: Resources,
-    systems: Schedule,
+    run_state: RunState,
+    // The run being played, if one has been started or continued
+    game: Option<Game>,
+    render_systems: Schedule,
+    // Records the keys of a new game, so the run can be replayed exactly
+    recorder: Option<Recorder>,
+    // Feeds recorded keys to the game instead of the keyboard
+    replay: Option<Replay>,
+    // While the full message log is open, how many entries it is scrolled back
+    log_scroll: Option<usize>,
+    // The player's preferences, changed on the options screen
+    key_bindings: KeyBindings,
+    settings: Settings,
+    // The classes offered for a new run come from here
+    templates: Templates,
+    leaderboard: Leaderboard,
+    // Once the run is over: how it scored, and where that put it in the high-score table
+    result: Option<(ScoreEntry, Option<usize>)>,
 }
 
 impl State {
+    // Open on the main menu.
     pub fn new() -> Self {
-        // legion stores all entities and components in the `World` struct
-        let mut ecs = World::default();
-        let mut resources = Resources::default();
-        let mut rng = RandomNumberGenerator::new();
-        let map_builder = MapBuilder::new(&mut rng);
-
-        // Calling spawn_player to add the player and their components to the ECS
-        spawn_player(&mut ecs, map_builder.player_start);
-
-        // Spawn monsters in each room except for the first room the player is in
-        map_builder
-            .rooms
+        Self {
+            run_state: RunState::MainMenu { selection: 0 },
+            game: None,
+            render_systems: build_render_scheduler(),
+            recorder: None,
+            replay: None,
+            log_scroll: None,
+            key_bindings: KeyBindings::load(),
+            settings: Settings::load(),
+            templates: Templates::load(),
+            leaderboard: Leaderboard::load(),
+            result: None,
+        }
+    }
+
+    // Play back a recorded run from its beginning.
+    pub fn replay(replay: Replay) -> Self {
+        let mut state = Self::new();
+        let game = Game::new(RandomNumberGenerator::seeded(replay.seed), &replay.class);
+        state.start(game, None, Some(replay));
+        state
+    }
+
+    // Start a new run as the given class, recording it to `REPLAY_FILE`.
+    fn new_game(&mut self, class: &str) {
+        // Pick the seed ourselves so that it can be written to the recording
+        let seed = RandomNumberGenerator::new().next_u64();
+        let recorder = match Recorder::create(REPLAY_FILE, seed, class, &self.key_bindings) {
+            Ok(recorder) => Some(recorder),
+            Err(err) => {
+                console::log(format!("Could not record {}: {}", REPLAY_FILE, err));
+                None
+            }
+        };
+
+        self.start(
+            Game::new(RandomNumberGenerator::seeded(seed), class),
+            recorder,
+            None,
+        );
+    }
+
+    fn start(&mut self, game: Game, recorder: Option<Recorder>, replay: Option<Replay>) {
+        let mut game = game;
+        game.set_key_bindings(self.key_bindings.clone());
+
+        self.game = Some(game);
+        self.recorder = recorder;
+        self.replay = replay;
+        self.log_scroll = None;
+        self.result = None;
+        self.run_state = RunState::Playing;
+    }
+
+    fn save(&self) {
+        let game = match &self.game {
+            Some(game) => game,
+            None => return,
+        };
+        // A replayed run must not overwrite the player's own save,
+        // and a finished run can't be resumed.
+        if self.replay.is_some() || game.turn_state().is_over() {
+            return;
+        }
+
+        if let Err(err) = save_game(&game.ecs, &game.resources) {
+            console::log(format!("Could not save {}: {}", SAVE_FILE, err));
+        }
+    }
+
+    // Resume the saved run. Returns whether there was one to resume.
+    fn load(&mut self) -> bool {
+        match load_game() {
+            Ok((ecs, resources)) => {
+                // A recording can only be replayed from a new game
+                self.start(Game::with_world(ecs, resources), None, None);
+                true
+            }
+            Err(err) => {
+                console::log(format!("Could not load {}: {}", SAVE_FILE, err));
+                false
+            }
+        }
+    }
+
+    // Pick the key to feed to the game this tick, recording it or taking it from the replay.
+    // Keys without a name, like Shift or Alt, couldn't be read back from the recording,
+    // so they are never fed to the game; nothing in the game uses them anyway.
+    fn next_key(&mut self, key: Option<VirtualKeyCode>) -> Option<VirtualKeyCode> {
+        // Keys only matter while the game awaits input; the other turn states ignore them
+        if !self.game.as_ref()?.turn_state().awaits_input() {
+            return None;
+        }
+
+        // The replay's own bindings are used, whatever the player's are
+        if let (Some(replay), Some(game)) = (&mut self.replay, &mut self.game) {
+            loop {
+                match replay.next_step() {
+                    Some(ReplayStep::Key(key)) => return Some(key),
+                    Some(ReplayStep::Bindings(bindings)) => game.set_key_bindings(bindings),
+                    None => {
+                        // The player takes over, with their own bindings
+                        console::log("Replay finished");
+                        self.replay = None;
+                        game.set_key_bindings(self.key_bindings.clone());
+                        return None;
+                    }
+                }
+            }
+        }
+
+        let key = key.filter(|key| is_named_key(*key));
+        if let (Some(recorder), Some(key)) = (&mut self.recorder, key) {
+            if let Err(err) = recorder.record(key) {
+                console::log(format!("Could not record {}: {}", REPLAY_FILE, err));
+                self.recorder = None;
+            }
+        }
+        key
+    }
+
+    // The player has died or won: the save is gone, and the run's score goes in the
+    // high-score table. The screen shows how the run went, and any key returns to the main menu.
+    fn game_over(&mut self, ctx: &mut BTerm, key: Option<VirtualKeyCode>) {
+        let game = match &self.game {
+            Some(game) => game,
+            None => return,
+        };
+        if self.result.is_none() {
+            let entry = game.score_entry();
+            // A replayed run was scored when it was played, and mustn't touch the player's save
+            let rank = if self.replay.is_none() {
+                delete_save();
+                let rank = self.leaderboard.add(entry.clone());
+                if let Err(err) = self.leaderboard.save() {
+                    console::log(format!("Could not save {}: {}", LEADERBOARD_FILE, err));
+                }
+                rank
+            } else {
+                None
+            };
+            self.result = Some((entry, rank));
+        }
+        let (entry, rank) = match &self.result {
+            Some(result) => result,
+            None => return,
+        };
+
+        ctx.set_active_console(2);
+        if game.turn_state() == TurnState::Victory {
+            ctx.print_color_centered(
+                8,
+                GOLD,
+                BLACK,
+                "You have won! The Amulet of the Deep is yours.",
+            );
+        } else {
+            ctx.print_color_centered(8, RED, BLACK, "You have died.");
+            ctx.print_color_centered(10, WHITE, BLACK, &entry.fate);
+        }
+        ctx.print_color_centered(13, YELLOW, BLACK, format!("Score: {}", entry.score));
+        ctx.print_color_centered(
+            15,
+            WHITE,
+            BLACK,
+            format!(
+                "The {} reached depth {} of {} in {} turns.",
+                entry.class,
+                entry.depth,
+                FINAL_DEPTH + 1,
+                entry.turns
+            ),
+        );
+        let kills = match game.resources.get::<RunStats>() {
+            Some(stats) if !stats.kills.is_empty() => stats
+                .kills
+                .iter()
+                .map(|(name, count)| format!("{} x{}", name, count))
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => "nothing".to_string(),
+        };
+        ctx.print_color_centered(
+            17,
+            WHITE,
+            BLACK,
+            format!("Items used: {}", entry.items_used),
+        );
+        ctx.print_color_centered(19, WHITE, BLACK, format!("Monsters killed: {}", kills));
+
+        draw_high_scores(ctx, &self.leaderboard, 24, *rank);
+        ctx.print_color_centered(HUD_HEIGHT - 4, GREY, BLACK, "Press any key");
+
+        if key.is_some() {
+            self.game = None;
+            self.result = None;
+            self.run_state = RunState::MainMenu { selection: 0 };
+        }
+    }
+
+    // The high-score table on its own; any key goes back to the main menu.
+    fn high_scores(&mut self, ctx: &mut BTerm, key: Option<VirtualKeyCode>) {
+        draw_high_scores(ctx, &self.leaderboard, 8, None);
+        ctx.print_color_centered(HUD_HEIGHT - 4, GREY, BLACK, "Press any key");
+
+        if key.is_some() {
+            self.run_state = RunState::MainMenu { selection: 0 };
+        }
+    }
+
+    // Show the whole message log, newest entries at the bottom.
+    // Movement keys scroll through it and the show-log key or Escape closes it.
+    fn show_log(&mut self, ctx: &mut BTerm, key: Option<VirtualKeyCode>) {
+        let game = match &self.game {
+            Some(game) => game,
+            None => return,
+        };
+        let log = game.resources.get::<GameLog>().unwrap();
+        let entries = log.entries();
+        let page = (HUD_HEIGHT - 4) as usize;
+        let max_scroll = entries.len().saturating_sub(page);
+        let mut scroll = self.log_scroll.unwrap_or(0).min(max_scroll);
+
+        match key.and_then(|key| game.action(key)) {
+            Some(Action::MoveNorth) => scroll = (scroll + 1).min(max_scroll),
+            Some(Action::MoveSouth) => scroll = scroll.saturating_sub(1),
+            Some(Action::ShowLog) => {
+                self.log_scroll = None;
+                return;
+            }
+            _ => match key {
+                Some(VirtualKeyCode::PageUp) => scroll = (scroll + page).min(max_scroll),
+                Some(VirtualKeyCode::PageDown) => scroll = scroll.saturating_sub(page),
+                Some(VirtualKeyCode::Escape) => {
+                    self.log_scroll = None;
+                    return;
+                }
+                _ => {}
+            },
+        }
+
+        ctx.set_active_console(2);
+        ctx.print_color_centered(0, YELLOW, BLACK, "Message log");
+        let end = entries.len() - scroll;
+        let start = end.saturating_sub(page);
+        entries[start..end]
             .iter()
-            .skip(1)
-            .map(|r| r.center())
-            .for_each(|pos| spawn_monster(&mut ecs, &mut rng, pos));
+            .enumerate()
+            .for_each(|(i, entry)| {
+                ctx.print_color(1, 2 + i as i32, entry.color, BLACK, &entry.text);
+            });
+        ctx.print_color_centered(
+            HUD_HEIGHT - 1,
+            GREY,
+            BLACK,
+            "Up/Down/PgUp/PgDn to scroll, Escape to close",
+        );
 
-        // Map and camera are part of our resources list
-        resources.insert(map_builder.map);
-        resources.insert(Camera::new(map_builder.player_start));
+        self.log_scroll = Some(scroll);
+    }
 
-        Self {
-            ecs,
-            resources,
-            systems: build_schedule(),
+    fn menu_input(&self, key: Option<VirtualKeyCode>) -> Option<MenuInput> {
+        key.and_then(|key| MenuInput::from_key(key, &self.key_bindings))
+    }
+
+    fn main_menu(&mut self, ctx: &mut BTerm, key: Option<VirtualKeyCode>, selection: usize) {
+        let entries = MainMenuItem::entries();
+        let selection = selection.min(entries.len() - 1);
+        let labels: Vec<String> = entries
+            .iter()
+            .map(|entry| entry.label().to_string())
+            .collect();
+        draw_menu(
+            ctx,
+            "Dungeon Crawler",
+            &labels,
+            selection,
+            "Up/Down to choose, Enter to select",
+        );
+
+        self.run_state = match self.menu_input(key) {
+            Some(MenuInput::Select) => match entries[selection] {
+                MainMenuItem::NewGame => RunState::ChooseClass { selection: 0 },
+                MainMenuItem::Continue => {
+                    if self.load() {
+                        return;
+                    }
+                    RunState::MainMenu { selection }
+                }
+                MainMenuItem::HighScores => RunState::HighScores,
+                MainMenuItem::Options => RunState::Options {
+                    selection: 0,
+                    paused: false,
+                    rebinding: false,
+                },
+                MainMenuItem::Quit => {
+                    ctx.quit();
+                    return;
+                }
+            },
+            Some(input) => RunState::MainMenu {
+                selection: move_selection(selection, entries.len(), input),
+            },
+            None => return,
+        };
+    }
+
+    // Each class is listed with what sets it apart; picking one starts the run.
+    fn choose_class(&mut self, ctx: &mut BTerm, key: Option<VirtualKeyCode>, selection: usize) {
+        let classes = &self.templates.classes;
+        let labels: Vec<String> = classes
+            .iter()
+            .map(|class| format!("{:<10} {}", class.name, class.description))
+            .collect();
+        draw_menu(
+            ctx,
+            "Choose your class",
+            &labels,
+            selection,
+            "Up/Down to choose, Enter to begin, Esc to go back",
+        );
+
+        self.run_state = match self.menu_input(key) {
+            Some(MenuInput::Back) => RunState::MainMenu { selection: 0 },
+            Some(MenuInput::Select) => {
+                let class = classes[selection].name.clone();
+                self.new_game(&class);
+                return;
+            }
+            Some(input) => RunState::ChooseClass {
+                selection: move_selection(selection, classes.len(), input),
+            },
+            None => return,
+        };
+    }
+
+    fn pause_menu(&mut self, ctx: &mut BTerm, key: Option<VirtualKeyCode>, selection: usize) {
+        let labels: Vec<String> = PauseMenuItem::ALL
+            .iter()
+            .map(|entry| entry.label().to_string())
+            .collect();
+        draw_menu(
+            ctx,
+            "Paused",
+            &labels,
+            selection,
+            "Up/Down to choose, Enter to select, Esc to resume",
+        );
+
+        self.run_state = match self.menu_input(key) {
+            Some(MenuInput::Back) => RunState::Playing,
+            Some(MenuInput::Select) => match PauseMenuItem::ALL[selection] {
+                PauseMenuItem::Resume => RunState::Playing,
+                PauseMenuItem::Options => RunState::Options {
+                    selection: 0,
+                    paused: true,
+                    rebinding: false,
+                },
+                PauseMenuItem::MainMenu => {
+                    self.save();
+                    self.game = None;
+                    RunState::MainMenu { selection: 0 }
+                }
+                PauseMenuItem::Quit => {
+                    self.save();
+                    ctx.quit();
+                    return;
+                }
+            },
+            Some(input) => RunState::Paused {
+                selection: move_selection(selection, PauseMenuItem::ALL.len(), input),
+            },
+            None => return,
+        };
+    }
+
+    // The first entry sets the font scale; the others rebind an action each.
+    // Changes are saved as soon as they are made.
+    fn options(
+        &mut self,
+        ctx: &mut BTerm,
+        key: Option<VirtualKeyCode>,
+        selection: usize,
+        paused: bool,
+        rebinding: bool,
+    ) {
+        let actions: Vec<Action> = self
+            .key_bindings
+            .bindings()
+            .map(|(action, _)| action)
+            .collect();
+        let mut labels = vec![format!(
+            "{:<20} {:.0}% (on restart)",
+            "Font scale",
+            self.settings.font_scale * 100.0
+        )];
+        labels.extend(self.key_bindings.bindings().map(|(action, keys)| {
+            let names: Vec<String> = keys.iter().map(|key| key_name(*key)).collect();
+            format!("{:<20} {}", action_label(action), names.join(", "))
+        }));
+        let help = if rebinding {
+            "Press the new key for this action, or Esc to keep the old ones"
+        } else {
+            "Left/Right to change the font scale, Enter to rebind, Esc to go back"
+        };
+        draw_menu(ctx, "Options", &labels, selection, help);
+
+        let key = match key {
+            Some(key) => key,
+            None => return,
+        };
+        // Escape keeps the action's old keys
+        if rebinding && key == VirtualKeyCode::Escape {
+            self.run_state = RunState::Options {
+                selection,
+                paused,
+                rebinding: false,
+            };
+            return;
+        }
+        // Keys without a name, like Shift, can't be written to `KEY_BINDINGS_FILE`,
+        // so the screen keeps waiting for one that can
+        if rebinding && !is_named_key(key) {
+            return;
+        }
+        if rebinding {
+            self.key_bindings.rebind(actions[selection - 1], key);
+            if let Err(err) = self.key_bindings.save() {
+                console::log(format!("Could not save {}: {}", KEY_BINDINGS_FILE, err));
+            }
+            // A run in progress plays on with the new bindings, and its recording notes
+            // where they took effect. A replay keeps the bindings it was recorded with.
+            if let (Some(game), None) = (&mut self.game, &self.replay) {
+                game.set_key_bindings(self.key_bindings.clone());
+                if let Some(recorder) = &mut self.recorder {
+                    if let Err(err) = recorder.record_bindings(&self.key_bindings) {
+                        console::log(format!("Could not record {}: {}", REPLAY_FILE, err));
+                        self.recorder = None;
+                    }
+                }
+            }
+            self.run_state = RunState::Options {
+                selection,
+                paused,
+                rebinding: false,
+            };
+            return;
+        }
+
+        let mut rebinding = false;
+        let mut selection = selection;
+        match MenuInput::from_key(key, &self.key_bindings) {
+            Some(MenuInput::Back) => {
+                self.run_state = if paused {
+                    RunState::Paused { selection: 0 }
+                } else {
+                    RunState::MainMenu { selection: 0 }
+                };
+                return;
+            }
+            Some(MenuInput::Left) if selection == 0 => self.change_font_scale(-1),
+            Some(MenuInput::Right) if selection == 0 => self.change_font_scale(1),
+            Some(MenuInput::Select) if selection > 0 => rebinding = true,
+            Some(input) => selection = move_selection(selection, labels.len(), input),
+            None => {}
+        }
+        self.run_state = RunState::Options {
+            selection,
+            paused,
+            rebinding,
+        };
+    }
+
+    fn change_font_scale(&mut self, step: i32) {
+        self.settings.step_font_scale(step);
+        if let Err(err) = self.settings.save() {
+            console::log(format!("Could not save {}: {}", SETTINGS_FILE, err));
+        }
+    }
+
+    // Run the game for a tick and draw it.
+    fn play(&mut self, ctx: &mut BTerm, key: Option<VirtualKeyCode>) {
+        // Saving and loading are handled here rather than by a system,
+        // as they replace the world and resources wholesale.
+        let key = match key {
+            Some(VirtualKeyCode::F5) => {
+                self.save();
+                None
+            }
+            Some(VirtualKeyCode::F9) => {
+                self.load();
+                None
+            }
+            key => key,
+        };
+        let turn_state = match &self.game {
+            Some(game) => game.turn_state(),
+            None => return,
+        };
+        if turn_state.is_over() {
+            self.game_over(ctx, key);
+            return;
         }
+
+        // The full message log is a view over the game, so it doesn't take a turn.
+        // It opens from normal play only, as the other screens may use its key as a letter.
+        // Keys are read with the game's bindings, which are the replay's during a replay.
+        let action = key.and_then(|key| self.game.as_ref()?.action(key));
+        if self.log_scroll.is_none()
+            && turn_state == TurnState::AwaitingInput
+            && action == Some(Action::ShowLog)
+        {
+            self.log_scroll = Some(0);
+            self.show_log(ctx, None);
+            return;
+        }
+        if self.log_scroll.is_some() {
+            self.show_log(ctx, key);
+            return;
+        }
+
+        // Cancelling with nothing to cancel pauses the game. The other screens that
+        // await input use the cancel key to close themselves.
+        if turn_state == TurnState::AwaitingInput && action == Some(Action::Cancel) {
+            self.run_state = RunState::Paused { selection: 0 };
+            self.draw_game(ctx);
+            return;
+        }
+
+        let key = self.next_key(key);
+
+        // The mouse position, in map console cells, is used by the tooltips
+        ctx.set_active_console(0);
+        let mouse = Point::from_tuple(ctx.mouse_pos());
+        if let Some(game) = &mut self.game {
+            game.resources.insert(mouse);
+
+            // Execute the systems for the current turn
+            game.tick(key);
+        }
+        self.draw_game(ctx);
+    }
+
+    // Submit the game's draw buffers, then render them.
+    fn draw_game(&mut self, ctx: &mut BTerm) {
+        if let Some(game) = &mut self.game {
+            self.render_systems
+                .execute(&mut game.ecs, &mut game.resources);
+        }
+
+        // Render draw buffers
+        render_draw_buffer(ctx).expect("Render error");
     }
 }
 
 impl GameState for State {
     fn tick(&mut self, ctx: &mut BTerm) {
+        // Save the run when the window is closed, so it can be resumed next time
+        let mut close_requested = false;
+        INPUT.lock().for_each_message(|event| {
+            if event == BEvent::CloseRequested {
+                close_requested = true;
+            }
+        });
+        if close_requested {
+            self.save();
+            ctx.quit();
+            return;
+        }
+
         // Set active console to map layer and clear
         ctx.set_active_console(0);
         ctx.cls();
@@ -47,15 +604,26 @@ impl GameState for State {
         ctx.set_active_console(1);
         ctx.cls();
 
-        // Add ctx.key (which holds the keyboard state) as a resource
-        // to make the current keyboard state available to any system that requests it
-        // When a resource is inserted into Legion’s resource handler, it replaces any existing resource of the same type
-        self.resources.insert(ctx.key);
-
-        // Execute systems (which also submits draw buffers)
-        self.systems.execute(&mut self.ecs, &mut self.resources);
+        // Set active console to HUD layer and clear
+        ctx.set_active_console(2);
+        ctx.cls();
 
-        // Render draw buffers
-        render_draw_buffer(ctx).expect("Render error");
+        let key = ctx.key;
+        match self.run_state {
+            RunState::MainMenu { selection } => self.main_menu(ctx, key, selection),
+            RunState::ChooseClass { selection } => self.choose_class(ctx, key, selection),
+            RunState::Playing => self.play(ctx, key),
+            RunState::HighScores => self.high_scores(ctx, key),
+            RunState::Paused { selection } => {
+                // The frozen game is drawn first, with the menu over it
+                self.draw_game(ctx);
+                self.pause_menu(ctx, key, selection);
+            }
+            RunState::Options {
+                selection,
+                paused,
+                rebinding,
+            } => self.options(ctx, key, selection, paused, rebinding),
+        }
     }
 }
@@ -0,0 +1,319 @@
+use crate::prelude::*;
+
+use super::monster_ai::step_towards;
+
+// How much damage the boss's area attack deals to everything caught in it
+const AREA_DAMAGE: i32 = 3;
+// Turns between special abilities, and between them once the boss is enraged
+const COOLDOWN: i32 = 4;
+const ENRAGED_COOLDOWN: i32 = 3;
+// How many minions the boss calls at a time
+const SUMMON_COUNT: usize = 2;
+
+// The boss fights in two phases. It waits in its lair until it sees the player,
+// then closes in and attacks like any other monster, and every few turns uses a
+// special ability instead:
+//
+// - It marks the tiles around the player and slams them on its next turn,
+//   giving the player a turn to get out of the way.
+// - Once it is down to half health it is enraged: it uses its abilities more
+//   often, and calls minions to its side instead of slamming.
+#[system]
+#[read_component(Point)]
+#[read_component(Player)]
+#[read_component(FieldOfView)]
+#[read_component(Health)]
+#[read_component(Name)]
+#[read_component(Damage)]
+#[write_component(Boss)]
+pub fn boss(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &Map,
+    #[resource] occupancy: &Occupancy,
+    #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] templates: &Templates,
+    #[resource] log: &mut GameLog,
+) {
+    let (player, player_pos) = match <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some((entity, pos)) => (*entity, *pos),
+        None => return,
+    };
+
+    <(
+        Entity,
+        &Point,
+        &FieldOfView,
+        &Health,
+        &Name,
+        &Damage,
+        &mut Boss,
+    )>::query()
+    .iter_mut(ecs)
+    .for_each(|(entity, pos, fov, health, name, damage, boss)| {
+        // Last turn's warning comes true, whether or not the player heeded it
+        if !boss.telegraphed.is_empty() {
+            log.add(format!("The {} slams the ground!", name.0), ORANGE);
+            boss.telegraphed
+                .drain(..)
+                .flat_map(|tile| occupancy.entities_at(tile).to_vec())
+                .filter(|victim| victim != entity)
+                .for_each(|victim| {
+                    commands.push((
+                        (),
+                        WantsToAttack {
+                            attacker: *entity,
+                            victim,
+                            damage: AREA_DAMAGE,
+                            inflicts: None,
+                        },
+                    ));
+                });
+            return;
+        }
+
+        if !boss.engaged {
+            if !fov.visible_tiles.contains(&player_pos) {
+                return;
+            }
+            boss.engaged = true;
+            boss.cooldown = COOLDOWN;
+            log.add(format!("The {} roars a challenge!", name.0), RED);
+        }
+        if !boss.enraged && health.current * 2 <= health.max {
+            boss.enraged = true;
+            log.add(format!("The {} flies into a rage!", name.0), RED);
+        }
+
+        boss.cooldown -= 1;
+        if boss.cooldown <= 0 {
+            boss.cooldown = if boss.enraged {
+                ENRAGED_COOLDOWN
+            } else {
+                COOLDOWN
+            };
+
+            let minion = templates
+                .monster(&name.0)
+                .and_then(|template| template.summons.as_deref())
+                .and_then(|summons| templates.monster(summons))
+                .cloned();
+            match (boss.enraged, minion) {
+                (true, Some(minion)) => {
+                    let spots = free_tiles_around(map, occupancy, *pos, SUMMON_COUNT);
+                    summon(commands, rng, minion, spots, player_pos);
+                    log.add(format!("The {} calls for help!", name.0), ORANGE);
+                }
+                _ => {
+                    boss.telegraphed = area_around(map, player_pos);
+                    log.add(format!("The {} raises its club...", name.0), ORANGE);
+                }
+            }
+            return;
+        }
+
+        let destination = match step_towards(map, *pos, player_pos) {
+            Some(destination) => destination,
+            None => return,
+        };
+        if destination == player_pos {
+            commands.push((
+                (),
+                WantsToAttack {
+                    attacker: *entity,
+                    victim: player,
+                    damage: damage.0,
+                    inflicts: None,
+                },
+            ));
+        } else if map.can_reach(*pos, destination) && !occupancy.is_occupied(destination) {
+            commands.push((
+                (),
+                WantsToMove {
+                    entity: *entity,
+                    destination,
+                },
+            ));
+        }
+    });
+}
+
+// The tiles around `center` that the area attack can reach, including `center` itself.
+fn area_around(map: &Map, center: Point) -> Vec<Point> {
+    (-1..=1)
+        .flat_map(|y| (-1..=1).map(move |x| center + Point::new(x, y)))
+        .filter(|tile| map.can_enter_tile(*tile))
+        .collect()
+}
+
+// Up to `count` empty tiles next to `center`, for minions to appear on.
+fn free_tiles_around(map: &Map, occupancy: &Occupancy, center: Point, count: usize) -> Vec<Point> {
+    area_around(map, center)
+        .into_iter()
+        .filter(|tile| !occupancy.is_occupied(*tile))
+        .take(count)
+        .collect()
+}
+
+// Spawn a minion on each of `spots`, already hunting the player.
+// Spawning needs the whole world, so it waits until the command buffer is flushed.
+fn summon(
+    commands: &mut CommandBuffer,
+    rng: &mut RandomNumberGenerator,
+    minion: MonsterTemplate,
+    spots: Vec<Point>,
+    player_pos: Point,
+) {
+    // The minions' own dice are seeded from the game's, keeping runs reproducible
+    let seed = rng.next_u64();
+    commands.exec_mut(move |ecs| {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        spots.iter().for_each(|pos| {
+            let monster = spawn_monster(ecs, &mut rng, &minion, *pos);
+            if let Some(mut entry) = ecs.entry(monster) {
+                entry.add_component(MonsterAi {
+                    state: AiState::Hunt {
+                        last_seen: player_pos,
+                    },
+                });
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An open map with the player at (10, 10) and the boss at `boss_pos`, in the given state,
+    // seeing the player if `sees_player`.
+    fn setup(boss_pos: Point, boss: Boss, sees_player: bool) -> (World, Resources, Entity) {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        let map = Map::new(20, 20);
+        let player_pos = Point::new(10, 10);
+        resources.insert(Camera::new(player_pos, 20, 20, &map));
+        resources.insert(map);
+        resources.insert(Occupancy::default());
+        resources.insert(GameLog::default());
+        resources.insert(RunStats::default());
+        resources.insert(TurnState::MonsterTurn);
+        let templates = Templates::load();
+        let mut rng = RandomNumberGenerator::seeded(1);
+
+        ecs.push((
+            Player { map_level: 0 },
+            player_pos,
+            Health {
+                current: 30,
+                max: 30,
+            },
+        ));
+        spawn_boss(&mut ecs, &mut rng, templates.boss().unwrap(), boss_pos);
+        let entity = <Entity>::query()
+            .filter(component::<Boss>())
+            .iter(&ecs)
+            .copied()
+            .next()
+            .unwrap();
+        let mut entry = ecs.entry(entity).unwrap();
+        entry.add_component(boss);
+        if sees_player {
+            let fov = entry.get_component_mut::<FieldOfView>().unwrap();
+            fov.visible_tiles.insert(player_pos);
+        }
+
+        resources.insert(templates);
+        resources.insert(rng);
+        (ecs, resources, entity)
+    }
+
+    fn boss_state(ecs: &World, boss: Entity) -> Boss {
+        ecs.entry_ref(boss)
+            .unwrap()
+            .get_component::<Boss>()
+            .unwrap()
+            .clone()
+    }
+
+    fn run_turn(ecs: &mut World, resources: &mut Resources) {
+        resources.insert(TurnState::MonsterTurn);
+        build_monster_scheduler().execute(ecs, resources);
+    }
+
+    #[test]
+    fn the_boss_waits_until_it_sees_the_player_and_is_enraged_at_half_health() {
+        let (mut ecs, mut resources, boss) = setup(Point::new(3, 3), Boss::default(), false);
+
+        run_turn(&mut ecs, &mut resources);
+        assert!(!boss_state(&ecs, boss).engaged);
+
+        let mut entry = ecs.entry(boss).unwrap();
+        entry
+            .get_component_mut::<FieldOfView>()
+            .unwrap()
+            .visible_tiles
+            .insert(Point::new(10, 10));
+        let health = entry.get_component_mut::<Health>().unwrap();
+        health.current = health.max / 2;
+        run_turn(&mut ecs, &mut resources);
+
+        let state = boss_state(&ecs, boss);
+        assert!(state.engaged && state.enraged);
+    }
+
+    #[test]
+    fn a_telegraphed_slam_lands_on_the_next_turn() {
+        let engaged = Boss {
+            engaged: true,
+            cooldown: 1,
+            ..Boss::default()
+        };
+        let (mut ecs, mut resources, boss) = setup(Point::new(3, 3), engaged, true);
+        let player_health = |ecs: &mut World| {
+            <&Health>::query()
+                .filter(component::<Player>())
+                .iter(ecs)
+                .next()
+                .unwrap()
+                .current
+        };
+
+        run_turn(&mut ecs, &mut resources);
+        assert!(boss_state(&ecs, boss)
+            .telegraphed
+            .contains(&Point::new(10, 10)));
+        assert_eq!(player_health(&mut ecs), 30);
+
+        run_turn(&mut ecs, &mut resources);
+        assert!(boss_state(&ecs, boss).telegraphed.is_empty());
+        assert_eq!(player_health(&mut ecs), 30 - AREA_DAMAGE);
+    }
+
+    #[test]
+    fn summoned_minions_take_up_their_tiles_straight_away() {
+        let enraged = Boss {
+            engaged: true,
+            enraged: true,
+            cooldown: 1,
+            telegraphed: Vec::new(),
+        };
+        let (mut ecs, mut resources, _) = setup(Point::new(3, 3), enraged, true);
+
+        run_turn(&mut ecs, &mut resources);
+
+        let minions: Vec<(Entity, Point)> = <(Entity, &Point, &MonsterAi)>::query()
+            .iter(&ecs)
+            .map(|(entity, pos, _)| (*entity, *pos))
+            .collect();
+        assert_eq!(minions.len(), SUMMON_COUNT);
+        let occupancy = resources.get::<Occupancy>().unwrap();
+        minions
+            .iter()
+            .for_each(|(minion, pos)| assert!(occupancy.entities_at(*pos).contains(minion)));
+    }
+}
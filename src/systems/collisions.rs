@@ -1,21 +0,0 @@
-use crate::prelude::*;
-
-// `CommandBuffer` is a special container to insert instructions for
-// Legion to perform after the system is finished. We use it to remove entities from the game.
-#[system]
-#[read_component(Point)]
-#[read_component(Player)]
-#[read_component(Enemy)]
-pub fn collisions(ecs: &mut SubWorld, commands: &mut CommandBuffer) {
-    let mut player_pos = Point::zero();
-    let mut players = <&Point>::query().filter(component::<Player>());
-    players.iter(ecs).for_each(|pos| player_pos = *pos);
-
-    let mut enemies = <(Entity, &Point)>::query().filter(component::<Enemy>());
-    enemies
-        .iter(ecs)
-        .filter(|(_, pos)| **pos == player_pos)
-        .for_each(|(entity, _)| {
-            commands.remove(*entity);
-        });
-}
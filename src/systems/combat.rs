@@ -0,0 +1,248 @@
+use crate::prelude::*;
+
+// Resolve attacks: each attack deals its own damage. Monsters that run out of
+// health are removed from the game; the player's death is picked up by `end_turn`.
+// An attack that inflicts a status effect applies it to a victim that survives.
+// The player earns experience for the monsters they kill, and each kill and the
+// player's death are noted in the run's stats.
+// Worn armour takes its defense off every hit, down to no damage at all.
+// Monsters drop the gold they carry where they die, and the boss drops the Amulet.
+#[system]
+#[read_component(WantsToAttack)]
+#[read_component(Player)]
+#[read_component(Point)]
+#[read_component(Name)]
+#[read_component(XpValue)]
+#[read_component(Equipped)]
+#[read_component(Defense)]
+#[read_component(Gold)]
+#[read_component(Boss)]
+#[write_component(Health)]
+#[write_component(StatusEffects)]
+#[write_component(Experience)]
+pub fn combat(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] occupancy: &mut Occupancy,
+    #[resource] log: &mut GameLog,
+    #[resource] stats: &mut RunStats,
+) {
+    let mut attackers = <(Entity, &WantsToAttack)>::query();
+    let attacks: Vec<(Entity, WantsToAttack)> = attackers
+        .iter(ecs)
+        .map(|(message, attack)| (*message, *attack))
+        .collect();
+
+    attacks.iter().for_each(|(message, attack)| {
+        let (attacker, victim) = (&attack.attacker, &attack.victim);
+        let attacker_name = describe(ecs, *attacker);
+        let victim_name = describe(ecs, *victim);
+        let victim_is_player = is_player(ecs, *victim);
+        let mut killed = false;
+        let damage = (attack.damage - defense_of(ecs, *victim)).max(0);
+
+        if let Ok(mut victim_entry) = ecs.entry_mut(*victim) {
+            // Several attackers may target the same victim; the dead stay dead
+            let alive = victim_entry
+                .get_component::<Health>()
+                .is_ok_and(|health| health.current > 0);
+            if let (true, Ok(health)) = (alive, victim_entry.get_component_mut::<Health>()) {
+                health.current -= damage;
+
+                let color = if victim_is_player { RED } else { WHITE };
+                log.add(
+                    format!(
+                        "{} {} {} for {}.",
+                        capitalize(&attacker_name),
+                        if attacker_name == "you" {
+                            "hit"
+                        } else {
+                            "hits"
+                        },
+                        victim_name,
+                        damage
+                    ),
+                    color,
+                );
+
+                if health.current < 1 {
+                    if victim_is_player {
+                        log.add("You die...", RED);
+                        stats.died(format!("Killed by {}", attacker_name));
+                    } else {
+                        killed = true;
+                    }
+                }
+            }
+
+            let survived = victim_entry
+                .get_component::<Health>()
+                .is_ok_and(|health| health.current > 0);
+            if let (true, true, Some(effect), Ok(status)) = (
+                alive,
+                survived,
+                attack.inflicts,
+                victim_entry.get_component_mut::<StatusEffects>(),
+            ) {
+                status.add(effect);
+                log.add(
+                    format!(
+                        "{} {} {}.",
+                        capitalize(&victim_name),
+                        if victim_is_player { "are" } else { "is" },
+                        effect.kind.name().to_lowercase()
+                    ),
+                    MAGENTA,
+                );
+            }
+        }
+
+        if killed {
+            monster_died(
+                ecs, commands, occupancy, log, stats, *victim, *attacker, "dies",
+            );
+        }
+
+        commands.remove(*message);
+    });
+}
+
+// Everything that happens when a monster dies, whatever killed it: the death is logged,
+// with `how` it died, the monster is removed from the game, dropping its loot, and `killer`
+// earns its experience. Only the player has experience, so monsters killing each other earn nothing.
+// The player's kills are counted in the run's stats.
+#[allow(clippy::too_many_arguments)]
+pub fn monster_died(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    occupancy: &mut Occupancy,
+    log: &mut GameLog,
+    stats: &mut RunStats,
+    monster: Entity,
+    killer: Entity,
+    how: &str,
+) {
+    log.add(
+        format!("{} {}.", capitalize(&describe(ecs, monster)), how),
+        GREEN,
+    );
+    let (pos, xp_value, gold, boss, kind) = match ecs.entry_ref(monster) {
+        Ok(entry) => (
+            entry.get_component::<Point>().ok().copied(),
+            entry.get_component::<XpValue>().map_or(0, |xp| xp.0),
+            entry.get_component::<Gold>().map_or(0, |gold| gold.0),
+            entry.get_component::<Boss>().is_ok(),
+            entry
+                .get_component::<Name>()
+                .ok()
+                .map(|name| name.0.clone()),
+        ),
+        Err(_) => (None, 0, 0, false, None),
+    };
+    if let Some(pos) = pos {
+        occupancy.remove(pos, monster);
+        spawn_loot(commands, pos, gold, boss);
+    }
+    commands.remove(monster);
+    if let (true, Some(kind)) = (is_player(ecs, killer), &kind) {
+        stats.add_kill(kind);
+    }
+
+    if xp_value > 0 {
+        if let Ok(mut killer_entry) = ecs.entry_mut(killer) {
+            if let Ok(experience) = killer_entry.get_component_mut::<Experience>() {
+                experience.gain(xp_value);
+                log.add(format!("You gain {} experience.", xp_value), CYAN);
+            }
+        }
+    }
+}
+
+// The total defense of everything `entity` wears.
+fn defense_of(ecs: &SubWorld, entity: Entity) -> i32 {
+    <(&Equipped, &Defense)>::query()
+        .iter(ecs)
+        .filter(|(equipped, _)| equipped.owner == entity)
+        .map(|(_, defense)| defense.0)
+        .sum()
+}
+
+fn is_player(ecs: &SubWorld, entity: Entity) -> bool {
+    ecs.entry_ref(entity)
+        .is_ok_and(|entry| entry.get_component::<Player>().is_ok())
+}
+
+// How an entity is referred to in the log: "you" for the player, "the Goblin" otherwise.
+fn describe(ecs: &SubWorld, entity: Entity) -> String {
+    if is_player(ecs, entity) {
+        return "you".to_string();
+    }
+
+    ecs.entry_ref(entity)
+        .ok()
+        .and_then(|entry| entry.get_component::<Name>().ok().cloned())
+        .map_or_else(|| "something".to_string(), |name| format!("the {}", name.0))
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn killing_a_monster_earns_the_player_its_experience() {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Occupancy::default());
+        resources.insert(GameLog::default());
+        resources.insert(RunStats::default());
+
+        let player = ecs.push((
+            Player { map_level: 0 },
+            Point::new(1, 1),
+            Health {
+                current: 10,
+                max: 10,
+            },
+            Experience::new(),
+        ));
+        let monster = ecs.push((
+            Name("Goblin".to_string()),
+            Point::new(2, 1),
+            Health { current: 2, max: 2 },
+            XpValue(12),
+        ));
+        ecs.push((
+            (),
+            WantsToAttack {
+                attacker: player,
+                victim: monster,
+                damage: 3,
+                inflicts: None,
+            },
+        ));
+
+        Schedule::builder()
+            .add_system(combat_system())
+            .build()
+            .execute(&mut ecs, &mut resources);
+
+        assert!(ecs.entry(monster).is_none());
+        let experience = *ecs
+            .entry(player)
+            .unwrap()
+            .get_component::<Experience>()
+            .unwrap();
+        // 12 experience is past the 10 needed for level 2
+        assert_eq!((experience.xp, experience.pending_level_ups), (12, 1));
+        let stats = resources.get::<RunStats>().unwrap();
+        assert_eq!(stats.kills.get("Goblin"), Some(&1));
+    }
+}
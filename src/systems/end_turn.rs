@@ -0,0 +1,96 @@
+use crate::prelude::*;
+
+// Advance the turn state once the systems for the current turn have run.
+// The game is over as soon as the player runs out of health, and a player who
+// stepped onto the stairs goes down to the next level. Creatures with an extra
+// action from haste get to act again before the turn moves on. Picking up the Amulet
+// wins the game. Before the player gets to act again, they pick the rewards for any
+// levels they have gained. The run's turns are counted as the monsters' turn ends, so
+// the extra actions haste grants don't count.
+#[system]
+#[read_component(Health)]
+#[read_component(Player)]
+#[read_component(Point)]
+#[read_component(Experience)]
+#[read_component(Carried)]
+#[read_component(Amulet)]
+#[write_component(StatusEffects)]
+pub fn end_turn(
+    ecs: &mut SubWorld,
+    #[resource] map: &Map,
+    #[resource] turn_state: &mut TurnState,
+    #[resource] stats: &mut RunStats,
+) {
+    let player_dead = <&Health>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .any(|health| health.current < 1);
+    let on_exit = <&Point>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .any(|pos| map.try_index(*pos).map(|idx| map.tiles[idx]) == Some(TileType::Exit));
+    let player = <Entity>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+        .copied();
+    let has_amulet = <&Carried>::query()
+        .filter(component::<Amulet>())
+        .iter(ecs)
+        .any(|carried| Some(carried.0) == player);
+    let leveled_up = <&Experience>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .any(|experience| experience.pending_level_ups > 0);
+
+    if *turn_state == TurnState::MonsterTurn {
+        stats.turns += 1;
+    }
+
+    let new_state = match turn_state {
+        _ if player_dead => TurnState::GameOver,
+        TurnState::AwaitingInput
+        | TurnState::Targeting { .. }
+        | TurnState::Equipment
+        | TurnState::Inventory { .. }
+        | TurnState::Spells
+        | TurnState::LevelUp
+        | TurnState::NextLevel
+        | TurnState::GameOver
+        | TurnState::Victory => return,
+        TurnState::PlayerTurn if has_amulet => TurnState::Victory,
+        TurnState::PlayerTurn if on_exit => TurnState::NextLevel,
+        TurnState::PlayerTurn => {
+            let extra_action = <&mut StatusEffects>::query()
+                .filter(component::<Player>())
+                .iter_mut(ecs)
+                .find(|status| status.extra_actions > 0);
+
+            match extra_action {
+                Some(status) => {
+                    status.extra_actions -= 1;
+                    TurnState::AwaitingInput
+                }
+                None => TurnState::MonsterTurn,
+            }
+        }
+        TurnState::MonsterTurn => {
+            let monsters_hasted = <&StatusEffects>::query()
+                .filter(!component::<Player>())
+                .iter(ecs)
+                .any(|status| status.extra_actions > 0);
+
+            if monsters_hasted {
+                TurnState::HastedMonsterTurn
+            } else {
+                TurnState::AwaitingInput
+            }
+        }
+        TurnState::HastedMonsterTurn => TurnState::AwaitingInput,
+    };
+
+    *turn_state = match new_state {
+        TurnState::AwaitingInput if leveled_up => TurnState::LevelUp,
+        new_state => new_state,
+    };
+}
@@ -1,21 +1,46 @@
 use crate::prelude::*;
 
-// The system requests read access to `Point` and `Render`
+// The system requests read access to `Point` and `Render`, and to the player's field of view
 #[system]
 #[read_component(Point)]
+#[read_component(Player)]
+#[read_component(FieldOfView)]
 #[read_component(Render)]
-pub fn entity_render(ecs: &SubWorld, #[resource] camera: &Camera) {
+#[read_component(Item)]
+#[read_component(Trap)]
+#[read_component(Hidden)]
+pub fn entity_render(ecs: &SubWorld, #[resource] camera: &Camera, #[resource] lighting: &Lighting) {
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(1);
 
     let offset = Point::new(camera.left_x, camera.top_y);
+    let player_fov = match <&FieldOfView>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(fov) => fov,
+        None => return,
+    };
 
     // Query for all entities that have a `Point` and `Render` component.
-    <(&Point, &Render)>::query()
+    // Hidden traps and anything the player can't see are left out. Traps and items are drawn first,
+    // so creatures standing on them stay visible. Everything is shaded by the light
+    // it stands in.
+    let mut entities: Vec<_> = <(&Point, &Render, Option<&Item>, Option<&Trap>)>::query()
+        .filter(!component::<Hidden>())
         .iter(ecs)
-        .for_each(|(pos, render)| {
-            draw_batch.set(*pos - offset, render.color, render.glyph);
-        });
+        .filter(|(pos, _, _, _)| camera.in_view(**pos) && player_fov.visible_tiles.contains(*pos))
+        .collect();
+    entities.sort_by_key(|(_, _, item, trap)| item.is_none() && trap.is_none());
+
+    entities.iter().for_each(|(pos, render, _, _)| {
+        let color = ColorPair::new(
+            lighting.shade(render.color.fg.to_rgb(), **pos),
+            render.color.bg,
+        );
+        draw_batch.set(**pos - offset, color, render.glyph);
+    });
 
     // 5000 is used as a sort order because the map may include up to 4000 elements. Thus leaving some room.
     draw_batch.submit(5000).expect("Batch error");
@@ -0,0 +1,190 @@
+use crate::prelude::*;
+
+// One line of the equipment screen: an item the player wears, or could wear.
+pub struct EquipmentEntry {
+    pub item: Entity,
+    pub name: String,
+    pub slot: EquipmentSlot,
+    pub worn: bool,
+    pub defense: i32,
+}
+
+// Everything `player` wears or carries that can be worn, grouped by slot
+// with the worn item first.
+pub fn equipment_list(ecs: &SubWorld, player: Entity) -> Vec<EquipmentEntry> {
+    let mut entries: Vec<EquipmentEntry> = <(Entity, &Equipped, &Name, Option<&Defense>)>::query()
+        .iter(ecs)
+        .filter(|(_, equipped, _, _)| equipped.owner == player)
+        .map(|(item, equipped, name, defense)| EquipmentEntry {
+            item: *item,
+            name: name.0.clone(),
+            slot: equipped.slot,
+            worn: true,
+            defense: defense.map_or(0, |defense| defense.0),
+        })
+        .collect();
+
+    <(Entity, &Carried, &Equippable, &Name, Option<&Defense>)>::query()
+        .iter(ecs)
+        .filter(|(_, carried, _, _, _)| carried.0 == player)
+        .for_each(|(item, _, equippable, name, defense)| {
+            entries.push(EquipmentEntry {
+                item: *item,
+                name: name.0.clone(),
+                slot: equippable.slot,
+                worn: false,
+                defense: defense.map_or(0, |defense| defense.0),
+            })
+        });
+
+    entries.sort_by_key(|entry| (entry.slot, !entry.worn));
+    entries
+}
+
+// Handle keys on the equipment screen. Each item is listed under a letter: picking
+// a worn item takes it off, back into the pack, and picking one from the pack puts
+// it on. Whatever was worn in that slot before is dropped at the player's feet.
+// Changing equipment takes a turn; Escape closes the screen. The equipment key doesn't,
+// as it may be the letter of an entry.
+#[system]
+#[read_component(Player)]
+#[read_component(Point)]
+#[read_component(Name)]
+#[read_component(Carried)]
+#[read_component(Equippable)]
+#[read_component(Equipped)]
+#[read_component(Defense)]
+pub fn equipment(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] key: &mut Option<VirtualKeyCode>,
+    #[resource] bindings: &KeyBindings,
+    #[resource] turn_state: &mut TurnState,
+    #[resource] log: &mut GameLog,
+) {
+    if *turn_state != TurnState::Equipment {
+        return;
+    }
+    let pressed = match key {
+        Some(key) => *key,
+        None => return,
+    };
+    if bindings.action(pressed) == Some(Action::Cancel) {
+        *turn_state = TurnState::AwaitingInput;
+        // The key is used up, so `player_input` doesn't open the screen straight back up
+        *key = None;
+        return;
+    }
+
+    let (player, player_pos) = match <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some((entity, pos)) => (*entity, *pos),
+        None => return,
+    };
+    let entries = equipment_list(ecs, player);
+    let entry = match usize::try_from(letter_to_option(pressed))
+        .ok()
+        .and_then(|index| entries.get(index))
+    {
+        Some(entry) => entry,
+        None => return,
+    };
+
+    if entry.worn {
+        commands.remove_component::<Equipped>(entry.item);
+        commands.add_component(entry.item, Carried(player));
+        log.add(format!("You take off the {}.", entry.name), WHITE);
+    } else {
+        if let Some(old) = entries
+            .iter()
+            .find(|old| old.worn && old.slot == entry.slot)
+        {
+            commands.remove_component::<Equipped>(old.item);
+            commands.add_component(old.item, player_pos);
+            log.add(format!("You drop the {}.", old.name), WHITE);
+        }
+        commands.remove_component::<Carried>(entry.item);
+        commands.add_component(
+            entry.item,
+            Equipped {
+                owner: player,
+                slot: entry.slot,
+            },
+        );
+        log.add(format!("You put on the {}.", entry.name), WHITE);
+    }
+
+    *turn_state = TurnState::PlayerTurn;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A player carrying the given armour, with the equipment screen open.
+    fn setup(armour: &[(&str, EquipmentSlot)]) -> (World, Resources, Vec<Entity>) {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        resources.insert(KeyBindings::default());
+        resources.insert(TurnState::Equipment);
+        resources.insert(GameLog::default());
+
+        let player = ecs.push((Player { map_level: 0 }, Point::new(1, 1)));
+        let items = armour
+            .iter()
+            .map(|(name, slot)| {
+                ecs.push((
+                    Item,
+                    Name(name.to_string()),
+                    Equippable { slot: *slot },
+                    Defense(1),
+                    Carried(player),
+                ))
+            })
+            .collect();
+        (ecs, resources, items)
+    }
+
+    fn press(ecs: &mut World, resources: &mut Resources, key: VirtualKeyCode) -> TurnState {
+        resources.insert(Some(key));
+        Schedule::builder()
+            .add_system(equipment_system())
+            .build()
+            .execute(ecs, resources);
+        let turn_state = *resources.get::<TurnState>().unwrap();
+        turn_state
+    }
+
+    #[test]
+    fn the_equipment_key_picks_the_entry_under_its_letter() {
+        use EquipmentSlot::*;
+        let (mut ecs, mut resources, items) = setup(&[
+            ("Leather Cap", Head),
+            ("Iron Helm", Head),
+            ("Chain Mail", Body),
+            ("Gauntlets", Hands),
+            ("Ring of Protection", Ring),
+        ]);
+
+        // E is both the equipment key and the letter of the ring, listed last
+        let turn_state = press(&mut ecs, &mut resources, VirtualKeyCode::E);
+
+        assert_eq!(turn_state, TurnState::PlayerTurn);
+        let ring = ecs.entry(items[4]).unwrap();
+        assert!(ring.get_component::<Equipped>().is_ok());
+    }
+
+    #[test]
+    fn escape_closes_the_screen() {
+        let (mut ecs, mut resources, items) = setup(&[("Leather Cap", EquipmentSlot::Head)]);
+
+        let turn_state = press(&mut ecs, &mut resources, VirtualKeyCode::Escape);
+
+        assert_eq!(turn_state, TurnState::AwaitingInput);
+        let cap = ecs.entry(items[0]).unwrap();
+        assert!(cap.get_component::<Carried>().is_ok());
+    }
+}
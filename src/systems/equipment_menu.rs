@@ -0,0 +1,83 @@
+use crate::prelude::*;
+
+use super::equipment::equipment_list;
+
+// Draw the equipment screen on the HUD console: every wearable item under the
+// letter that selects it, and the total protection of what is worn.
+#[system]
+#[read_component(Player)]
+#[read_component(Name)]
+#[read_component(Carried)]
+#[read_component(Equippable)]
+#[read_component(Equipped)]
+#[read_component(Defense)]
+pub fn equipment_menu(ecs: &SubWorld, #[resource] turn_state: &TurnState) {
+    if *turn_state != TurnState::Equipment {
+        return;
+    }
+    let player = match <Entity>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(player) => *player,
+        None => return,
+    };
+    let entries = equipment_list(ecs, player);
+
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(2);
+    let width = 60;
+    let height = entries.len().max(1) as i32 + 6;
+    let (x, y) = ((HUD_WIDTH - width) / 2, (HUD_HEIGHT - height) / 2);
+    draw_batch.draw_double_box(
+        Rect::with_size(x, y, width, height),
+        ColorPair::new(WHITE, BLACK),
+    );
+    draw_batch.print_color_centered(y + 1, "Equipment", ColorPair::new(YELLOW, BLACK));
+
+    if entries.is_empty() {
+        draw_batch.print_color(
+            Point::new(x + 2, y + 3),
+            "You have nothing to wear.",
+            ColorPair::new(GREY, BLACK),
+        );
+    }
+    entries.iter().enumerate().for_each(|(i, entry)| {
+        let letter = (b'a' + i as u8) as char;
+        let (status, color) = if entry.worn {
+            ("worn", WHITE)
+        } else {
+            ("in pack", GREY)
+        };
+        draw_batch.print_color(
+            Point::new(x + 2, y + 3 + i as i32),
+            format!(
+                "{}) {:<6} {:<20} +{} defense  ({})",
+                letter,
+                format!("{:?}", entry.slot),
+                entry.name,
+                entry.defense,
+                status
+            ),
+            ColorPair::new(color, BLACK),
+        );
+    });
+
+    let total: i32 = entries
+        .iter()
+        .filter(|entry| entry.worn)
+        .map(|entry| entry.defense)
+        .sum();
+    draw_batch.print_color_centered(
+        y + height - 2,
+        format!(
+            "Total defense: {}   Letter to wear or remove, Esc to close",
+            total
+        ),
+        ColorPair::new(GREY, BLACK),
+    );
+
+    // Drawn over the HUD
+    draw_batch.submit(10100).expect("Batch error");
+}
@@ -0,0 +1,84 @@
+use crate::prelude::*;
+
+// How far creatures can make things out in the dark
+const DARK_SIGHT: f32 = 2.0;
+
+// Recalculate the field of view of every entity that moved since it was last calculated.
+// Tiles too dark to see by are only seen from close up. Whatever the player sees is
+// remembered on the map.
+#[system]
+#[read_component(Point)]
+#[read_component(Player)]
+#[write_component(FieldOfView)]
+pub fn fov(ecs: &mut SubWorld, #[resource] map: &mut Map, #[resource] lighting: &Lighting) {
+    let mut views = <(&Point, &mut FieldOfView, Option<&Player>)>::query();
+
+    views
+        .iter_mut(ecs)
+        .filter(|(_, fov, _)| fov.is_dirty)
+        .for_each(|(pos, fov, player)| {
+            fov.visible_tiles = field_of_view_set(*pos, fov.radius, map)
+                .into_iter()
+                .filter(|tile| {
+                    lighting.is_lit(*tile)
+                        || DistanceAlg::Pythagoras.distance2d(*pos, *tile) <= DARK_SIGHT
+                })
+                .collect();
+            fov.is_dirty = false;
+
+            if player.is_some() {
+                fov.visible_tiles.iter().for_each(|tile| {
+                    if let Some(idx) = map.try_index(*tile) {
+                        map.revealed_tiles[idx] = true;
+                    }
+                });
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An open 20x5 map lit by `lights`, with the player at (1, 2) and a goblin at (8, 2).
+    fn sight_of_the_goblin(lights: &[(Point, LightSource)]) -> (bool, Map) {
+        let map = Map::new(20, 5);
+        let mut resources = Resources::default();
+        resources.insert(Lighting::compute(&map, lights));
+        resources.insert(map);
+
+        let mut ecs = World::default();
+        let player = ecs.push((
+            Player { map_level: 0 },
+            Point::new(1, 2),
+            FieldOfView::new(8),
+        ));
+
+        Schedule::builder()
+            .add_system(fov_system())
+            .build()
+            .execute(&mut ecs, &mut resources);
+
+        let entry = ecs.entry(player).unwrap();
+        let fov = entry.get_component::<FieldOfView>().unwrap();
+        assert!(fov.visible_tiles.contains(&Point::new(2, 2)));
+        let map = resources.remove::<Map>().unwrap();
+        (fov.visible_tiles.contains(&Point::new(8, 2)), map)
+    }
+
+    #[test]
+    fn a_creature_in_the_dark_is_only_seen_from_close_up() {
+        let (seen, map) = sight_of_the_goblin(&[]);
+        assert!(!seen);
+        assert!(map.revealed_tiles[map.map_index(2, 2)]);
+        assert!(!map.revealed_tiles[map.map_index(8, 2)]);
+
+        let torch = LightSource {
+            radius: 3,
+            color: (255, 200, 120),
+        };
+        let (seen, map) = sight_of_the_goblin(&[(Point::new(8, 2), torch)]);
+        assert!(seen);
+        assert!(map.revealed_tiles[map.map_index(8, 2)]);
+    }
+}
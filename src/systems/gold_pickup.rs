@@ -0,0 +1,44 @@
+use crate::prelude::*;
+
+// Gold needs no picking up: the player pockets any pile they walk over.
+#[system]
+#[read_component(Point)]
+#[read_component(Player)]
+#[read_component(Health)]
+#[write_component(Gold)]
+pub fn gold_pickup(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] log: &mut GameLog,
+) {
+    let player_pos = match <&Point>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(pos) => *pos,
+        None => return,
+    };
+
+    // Piles are the gold that nobody (nothing with health) is carrying
+    let piles: Vec<(Entity, i32)> = <(Entity, &Point, &Gold)>::query()
+        .filter(!component::<Health>())
+        .iter(ecs)
+        .filter(|(_, pos, _)| **pos == player_pos)
+        .map(|(pile, _, gold)| (*pile, gold.0))
+        .collect();
+    if piles.is_empty() {
+        return;
+    }
+
+    let amount: i32 = piles.iter().map(|(_, amount)| amount).sum();
+    if let Some(purse) = <&mut Gold>::query()
+        .filter(component::<Player>())
+        .iter_mut(ecs)
+        .next()
+    {
+        purse.0 += amount;
+        log.add(format!("You pick up {} gold.", amount), GOLD);
+    }
+    piles.iter().for_each(|(pile, _)| commands.remove(*pile));
+}
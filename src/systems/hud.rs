@@ -0,0 +1,138 @@
+use crate::prelude::*;
+
+// How many of the most recent log entries are shown at the bottom of the screen
+const LOG_LINES: usize = 5;
+
+// Draw the player's health, how hungry they are, their active status effects,
+// their class, mana and progress through the levels and the dungeon, their gold,
+// and the latest log entries on the HUD console. Once the boss has been engaged
+// its health is shown too, until it dies.
+#[system]
+#[read_component(Health)]
+#[read_component(Player)]
+#[read_component(StatusEffects)]
+#[read_component(Experience)]
+#[read_component(Hunger)]
+#[read_component(Gold)]
+#[read_component(Class)]
+#[read_component(Mana)]
+#[read_component(Boss)]
+#[read_component(Name)]
+pub fn hud(ecs: &SubWorld, #[resource] log: &GameLog) {
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(2);
+
+    if let Some(health) = <&Health>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        draw_batch.bar_horizontal(
+            Point::zero(),
+            HUD_WIDTH,
+            health.current,
+            health.max,
+            ColorPair::new(RED, BLACK),
+        );
+        draw_batch.print_color_centered(
+            0,
+            format!(" Health: {} / {} ", health.current, health.max),
+            ColorPair::new(WHITE, RED),
+        );
+    }
+
+    // How hungry the player is comes first, then each effect with the turns it
+    // has left, e.g. "Poisoned (3)"
+    let mut x = 1;
+    if let Some(hunger) = <&Hunger>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        let state = hunger.state();
+        let color = match state {
+            HungerState::WellFed => GREEN,
+            HungerState::Hungry => ORANGE,
+            HungerState::Weak | HungerState::Starving => RED,
+        };
+        draw_batch.print_color(Point::new(x, 1), state.name(), ColorPair::new(color, BLACK));
+        x += state.name().len() as i32 + 2;
+    }
+    if let Some(status) = <&StatusEffects>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        status.effects.iter().for_each(|effect| {
+            let color = match effect.kind {
+                StatusKind::Poison => GREEN,
+                StatusKind::Confusion => MAGENTA,
+                StatusKind::Haste => CYAN,
+            };
+            let text = format!("{} ({})", effect.kind.name(), effect.turns_remaining);
+            draw_batch.print_color(Point::new(x, 1), &text, ColorPair::new(color, BLACK));
+            x += text.len() as i32 + 2;
+        });
+    }
+
+    if let Some((player, class, experience, gold, mana)) =
+        <(&Player, &Class, &Experience, &Gold, &Mana)>::query()
+            .iter(ecs)
+            .next()
+    {
+        let text = format!(
+            "{}  Mana {}/{}  Level {}  XP {}/{}  Gold {}  Depth {}/{}",
+            class.0,
+            mana.current,
+            mana.max,
+            experience.level,
+            experience.xp,
+            Experience::xp_for_next_level(experience.level),
+            gold.0,
+            player.map_level + 1,
+            FINAL_DEPTH + 1
+        );
+        draw_batch.print_color(
+            Point::new(HUD_WIDTH - text.len() as i32 - 1, 1),
+            &text,
+            ColorPair::new(WHITE, BLACK),
+        );
+    }
+
+    // Below the line the targeting help is shown on
+    if let Some((boss, name, health)) = <(&Boss, &Name, &Health)>::query()
+        .iter(ecs)
+        .find(|(boss, ..)| boss.engaged)
+    {
+        let width = HUD_WIDTH / 2;
+        let x = (HUD_WIDTH - width) / 2;
+        draw_batch.bar_horizontal(
+            Point::new(x, 3),
+            width,
+            health.current,
+            health.max,
+            ColorPair::new(PURPLE, BLACK),
+        );
+        let color = if boss.enraged { RED } else { WHITE };
+        draw_batch.print_color_centered(
+            3,
+            format!(" {}: {} / {} ", name.0, health.current, health.max),
+            ColorPair::new(color, PURPLE),
+        );
+    }
+
+    // Newest entries at the bottom, older ones scrolling up and out of view
+    let entries = log.entries();
+    let recent = &entries[entries.len().saturating_sub(LOG_LINES)..];
+    let top = HUD_HEIGHT - 1 - recent.len() as i32;
+    recent.iter().enumerate().for_each(|(i, entry)| {
+        draw_batch.print_color(
+            Point::new(1, top + i as i32),
+            &entry.text,
+            ColorPair::new(entry.color, BLACK),
+        );
+    });
+
+    // The HUD is drawn on top of everything else
+    draw_batch.submit(10000).expect("Batch error");
+}
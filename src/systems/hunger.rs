@@ -0,0 +1,78 @@
+use crate::prelude::*;
+
+// The player gets a little hungrier every turn. Each step from well fed down to
+// starving is announced as it happens, and a starving player loses a point of
+// health every turn until they eat something.
+#[system]
+#[read_component(Player)]
+#[write_component(Hunger)]
+#[write_component(Health)]
+pub fn hunger(ecs: &mut SubWorld, #[resource] log: &mut GameLog, #[resource] stats: &mut RunStats) {
+    <(&mut Hunger, &mut Health)>::query()
+        .filter(component::<Player>())
+        .iter_mut(ecs)
+        .for_each(|(hunger, health)| {
+            let before = hunger.state();
+            hunger.food = (hunger.food - 1).max(0);
+
+            match hunger.state() {
+                state if state == before => {}
+                HungerState::Hungry => log.add("You are getting hungry.", ORANGE),
+                HungerState::Weak => log.add("You are weak with hunger.", ORANGE),
+                HungerState::Starving => log.add("You are starving!", RED),
+                HungerState::WellFed => {}
+            }
+
+            if hunger.state() == HungerState::Starving && health.current > 0 {
+                health.current -= 1;
+                if health.current < 1 {
+                    log.add("You starve to death...", RED);
+                    stats.died("Starved to death");
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_starving_player_loses_health_every_turn_until_they_die() {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        resources.insert(GameLog::default());
+        resources.insert(RunStats::default());
+        let player = ecs.push((
+            Player { map_level: 0 },
+            Hunger { food: 1 },
+            Health {
+                current: 2,
+                max: 10,
+            },
+        ));
+
+        let mut schedule = Schedule::builder().add_system(hunger_system()).build();
+        let mut health = Vec::new();
+        for _ in 0..3 {
+            schedule.execute(&mut ecs, &mut resources);
+            let entry = ecs.entry(player).unwrap();
+            assert_eq!(
+                entry.get_component::<Hunger>().unwrap().state(),
+                HungerState::Starving
+            );
+            health.push(entry.get_component::<Health>().unwrap().current);
+        }
+
+        // The last turn finds the player already dead
+        assert_eq!(health, vec![1, 0, 0]);
+        let log: Vec<String> = resources
+            .get::<GameLog>()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| entry.text.clone())
+            .collect();
+        assert_eq!(log, vec!["You are starving!", "You starve to death..."]);
+    }
+}
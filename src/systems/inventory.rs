@@ -0,0 +1,471 @@
+use crate::prelude::*;
+
+use super::player_input::visible_enemies;
+
+// Shops pay this fraction of an item's price when buying it from the player
+const SELL_DIVISOR: i32 = 2;
+// What any potion the player hasn't identified yet costs, so the price can't give it away
+const UNIDENTIFIED_PRICE: i32 = 15;
+
+// The items that can be picked in `mode`, with their real names, in the order the
+// inventory screen lists them. They are the ones `player` carries, except when buying,
+// when they are the ones the shopkeeper has for sale.
+pub fn inventory_list(
+    ecs: &SubWorld,
+    player: Entity,
+    mode: InventoryMode,
+    identification: &Identification,
+) -> Vec<(Entity, String)> {
+    let owner = match mode {
+        InventoryMode::Buy => <Entity>::query()
+            .filter(component::<Shopkeeper>())
+            .iter(ecs)
+            .next()
+            .copied(),
+        _ => Some(player),
+    };
+
+    let mut items: Vec<(Entity, String)> = <(
+        Entity,
+        &Carried,
+        &Name,
+        Option<&Potion>,
+        Option<&Food>,
+        Option<&Teaches>,
+    )>::query()
+    .iter(ecs)
+    .filter(|(_, carried, _, potion, food, teaches)| {
+        Some(carried.0) == owner
+            && match mode {
+                InventoryMode::Quaff => potion.is_some(),
+                InventoryMode::Eat => food.is_some(),
+                InventoryMode::Read => teaches.is_some(),
+                _ => true,
+            }
+    })
+    .map(|(item, _, name, ..)| (*item, name.0.clone()))
+    .collect();
+
+    items.sort_by_key(|(_, name)| identification.display_name(name));
+    items
+}
+
+// What the item called `name` costs in the shop, or what the shop pays for it.
+pub fn trade_price(
+    templates: &Templates,
+    identification: &Identification,
+    name: &str,
+    mode: InventoryMode,
+) -> i32 {
+    let price = if identification.is_identified(name) {
+        templates.price(name)
+    } else {
+        UNIDENTIFIED_PRICE
+    };
+    match mode {
+        InventoryMode::Sell => price / SELL_DIVISOR,
+        _ => price,
+    }
+}
+
+// Apply the effect of `potion` to `target`, who drank it or was splashed by it.
+pub fn apply_potion(ecs: &mut SubWorld, potion: Entity, target: Entity, log: &mut GameLog) {
+    let (inflicts, healing) = match ecs.entry_ref(potion) {
+        Ok(potion) => (
+            potion
+                .get_component::<Inflicts>()
+                .ok()
+                .map(|inflicts| inflicts.0),
+            potion
+                .get_component::<Healing>()
+                .ok()
+                .map(|healing| healing.0),
+        ),
+        Err(_) => return,
+    };
+    let mut target = match ecs.entry_mut(target) {
+        Ok(target) => target,
+        Err(_) => return,
+    };
+
+    let is_player = target.get_component::<Player>().is_ok();
+    let name = if is_player {
+        "You".to_string()
+    } else {
+        target.get_component::<Name>().map_or_else(
+            |_| "Something".to_string(),
+            |name| format!("The {}", name.0),
+        )
+    };
+
+    if let (Some(effect), Ok(status)) = (inflicts, target.get_component_mut::<StatusEffects>()) {
+        status.add(effect);
+        log.add(
+            format!(
+                "{} {} {}.",
+                name,
+                if is_player { "are" } else { "is" },
+                effect.kind.name().to_lowercase()
+            ),
+            CYAN,
+        );
+    }
+    if let (Some(amount), Ok(health)) = (healing, target.get_component_mut::<Health>()) {
+        health.current = (health.current + amount).min(health.max);
+        log.add(
+            format!(
+                "{} {} better.",
+                name,
+                if is_player { "feel" } else { "looks" }
+            ),
+            GREEN,
+        );
+    }
+}
+
+// Handle keys on the inventory screen. Each item that can be picked is listed under
+// a letter: depending on how the screen was opened, the player drinks or eats it, reads it,
+// drops it at their feet or readies it to throw and starts aiming. Drinking a potion
+// identifies every potion of its kind, and reading a book teaches its spell. Escape closes
+// the screen; the key that opened it doesn't, as it may be the letter of an item.
+// In the shop, the next-target key switches between buying and selling, and trading
+// doesn't take a turn.
+#[system]
+#[allow(clippy::too_many_arguments)]
+#[read_component(Player)]
+#[read_component(Point)]
+#[read_component(Name)]
+#[read_component(Carried)]
+#[read_component(Potion)]
+#[read_component(Inflicts)]
+#[read_component(Healing)]
+#[read_component(Food)]
+#[read_component(Enemy)]
+#[read_component(FieldOfView)]
+#[read_component(Shopkeeper)]
+#[read_component(Teaches)]
+#[write_component(KnownSpells)]
+#[write_component(Health)]
+#[write_component(StatusEffects)]
+#[write_component(Hunger)]
+#[write_component(Gold)]
+pub fn inventory(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] key: &mut Option<VirtualKeyCode>,
+    #[resource] bindings: &KeyBindings,
+    #[resource] identification: &mut Identification,
+    #[resource] templates: &Templates,
+    #[resource] turn_state: &mut TurnState,
+    #[resource] log: &mut GameLog,
+    #[resource] stats: &mut RunStats,
+) {
+    let mode = match turn_state {
+        TurnState::Inventory { mode } => *mode,
+        _ => return,
+    };
+    let pressed = match key {
+        Some(key) => *key,
+        None => return,
+    };
+    match bindings.action(pressed) {
+        Some(Action::Cancel) => {
+            *turn_state = TurnState::AwaitingInput;
+            // The key is used up, so `player_input` doesn't open the screen straight back up
+            *key = None;
+            return;
+        }
+        Some(Action::NextTarget) => {
+            match mode {
+                InventoryMode::Buy => {
+                    *turn_state = TurnState::Inventory {
+                        mode: InventoryMode::Sell,
+                    }
+                }
+                InventoryMode::Sell => {
+                    *turn_state = TurnState::Inventory {
+                        mode: InventoryMode::Buy,
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let (player, player_pos) = match <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some((entity, pos)) => (*entity, *pos),
+        None => return,
+    };
+    let items = inventory_list(ecs, player, mode, identification);
+    let (item, name) = match usize::try_from(letter_to_option(pressed))
+        .ok()
+        .and_then(|index| items.get(index))
+    {
+        Some((item, name)) => (*item, name.clone()),
+        None => return,
+    };
+    let display_name = identification.display_name(&name);
+
+    match mode {
+        InventoryMode::Quaff => {
+            log.add(format!("You drink the {}.", display_name), WHITE);
+            apply_potion(ecs, item, player, log);
+            if identification.identify(&name) {
+                log.add(format!("It was {}.", a_or_an(&name)), YELLOW);
+            }
+            commands.remove(item);
+            stats.items_used += 1;
+        }
+        InventoryMode::Eat => {
+            log.add(format!("You eat the {}.", display_name), WHITE);
+            let food = ecs.entry_ref(item).map_or(0, |item| {
+                item.get_component::<Food>().map_or(0, |food| food.0)
+            });
+            if let Ok(mut player) = ecs.entry_mut(player) {
+                if let Ok(hunger) = player.get_component_mut::<Hunger>() {
+                    hunger.eat(food);
+                    log.add(
+                        format!("You feel {}.", hunger.state().name().to_lowercase()),
+                        GREEN,
+                    );
+                }
+            }
+            commands.remove(item);
+            stats.items_used += 1;
+        }
+        InventoryMode::Read => {
+            let spell = ecs.entry_ref(item).map_or(String::new(), |item| {
+                item.get_component::<Teaches>()
+                    .map_or(String::new(), |teaches| teaches.0.clone())
+            });
+            if let Some(known) = <&mut KnownSpells>::query()
+                .filter(component::<Player>())
+                .iter_mut(ecs)
+                .next()
+            {
+                if known.0.contains(&spell) {
+                    log.add(format!("You already know {}.", spell), GREY);
+                    return;
+                }
+                known.0.push(spell.clone());
+            }
+            log.add(format!("You read the {}.", display_name), WHITE);
+            log.add(format!("You learn to cast {}!", spell), CYAN);
+            commands.remove(item);
+            stats.items_used += 1;
+        }
+        InventoryMode::Drop => {
+            commands.remove_component::<Carried>(item);
+            commands.add_component(item, player_pos);
+            log.add(format!("You drop the {}.", display_name), WHITE);
+        }
+        InventoryMode::Throw => {
+            // Aiming doesn't take a turn; throwing does, once the target is confirmed
+            commands.add_component(item, Throwing);
+            let cursor = <&FieldOfView>::query()
+                .filter(component::<Player>())
+                .iter(ecs)
+                .next()
+                .and_then(|fov| visible_enemies(ecs, player_pos, fov).into_iter().next())
+                .unwrap_or(player_pos);
+            *turn_state = TurnState::Targeting { cursor };
+            return;
+        }
+        InventoryMode::Buy | InventoryMode::Sell => {
+            trade(
+                ecs,
+                commands,
+                log,
+                player,
+                item,
+                &display_name,
+                trade_price(templates, identification, &name, mode),
+                mode,
+            );
+            return;
+        }
+    }
+
+    *turn_state = TurnState::PlayerTurn;
+}
+
+// Buy `item` from the shopkeeper, or sell it to them, for `price` if the buyer can afford it.
+#[allow(clippy::too_many_arguments)]
+fn trade(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    log: &mut GameLog,
+    player: Entity,
+    item: Entity,
+    display_name: &str,
+    price: i32,
+    mode: InventoryMode,
+) {
+    let shopkeeper = match <Entity>::query()
+        .filter(component::<Shopkeeper>())
+        .iter(ecs)
+        .next()
+    {
+        Some(shopkeeper) => *shopkeeper,
+        None => return,
+    };
+    let purse = match <&mut Gold>::query()
+        .filter(component::<Player>())
+        .iter_mut(ecs)
+        .next()
+    {
+        Some(purse) => purse,
+        None => return,
+    };
+
+    if mode == InventoryMode::Buy {
+        if purse.0 < price {
+            log.add(format!("You can't afford the {}.", display_name), GREY);
+            return;
+        }
+        purse.0 -= price;
+        commands.add_component(item, Carried(player));
+        log.add(
+            format!("You buy the {} for {} gold.", display_name, price),
+            GOLD,
+        );
+    } else {
+        purse.0 += price;
+        commands.add_component(item, Carried(shopkeeper));
+        log.add(
+            format!("You sell the {} for {} gold.", display_name, price),
+            GOLD,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A player with `gold` in the shop, carrying `wares`, and a shopkeeper carrying `stock`.
+    fn setup(gold: i32, wares: &[&str], stock: &[&str]) -> (World, Resources, Entity) {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        let templates = Templates::load();
+        resources.insert(Identification::new(
+            &mut RandomNumberGenerator::seeded(1),
+            &templates.potion_names(),
+        ));
+        resources.insert(templates);
+        resources.insert(KeyBindings::default());
+        resources.insert(GameLog::default());
+        resources.insert(RunStats::default());
+
+        let player = ecs.push((Player { map_level: 0 }, Point::new(1, 1), Gold(gold)));
+        let shopkeeper = ecs.push((Shopkeeper, Point::new(2, 1)));
+        for (owner, names) in [(player, wares), (shopkeeper, stock)] {
+            names.iter().for_each(|name| {
+                ecs.push((Item, Name(name.to_string()), Carried(owner)));
+            });
+        }
+        (ecs, resources, player)
+    }
+
+    fn press(ecs: &mut World, resources: &mut Resources, mode: InventoryMode, key: VirtualKeyCode) {
+        resources.insert(TurnState::Inventory { mode });
+        resources.insert(Some(key));
+        Schedule::builder()
+            .add_system(inventory_system())
+            .build()
+            .execute(ecs, resources);
+    }
+
+    fn gold(ecs: &World, player: Entity) -> i32 {
+        ecs.entry_ref(player)
+            .unwrap()
+            .get_component::<Gold>()
+            .unwrap()
+            .0
+    }
+
+    fn carried_by(ecs: &World, owner: Entity) -> Vec<String> {
+        let mut names: Vec<String> = <(&Carried, &Name)>::query()
+            .iter(ecs)
+            .filter(|(carried, _)| carried.0 == owner)
+            .map(|(_, name)| name.0.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn buying_takes_the_price_from_the_players_gold_if_they_can_afford_it() {
+        let (mut ecs, mut resources, player) = setup(45, &[], &["Chain Mail", "Ration"]);
+
+        // Chain Mail is listed first, at 40 gold, leaving too little for the Ration
+        press(
+            &mut ecs,
+            &mut resources,
+            InventoryMode::Buy,
+            VirtualKeyCode::A,
+        );
+        press(
+            &mut ecs,
+            &mut resources,
+            InventoryMode::Buy,
+            VirtualKeyCode::A,
+        );
+
+        assert_eq!(gold(&ecs, player), 5);
+        assert_eq!(carried_by(&ecs, player), vec!["Chain Mail"]);
+    }
+
+    #[test]
+    fn selling_pays_half_the_price_and_hands_the_item_over() {
+        let (mut ecs, mut resources, player) = setup(0, &["Chain Mail"], &[]);
+
+        press(
+            &mut ecs,
+            &mut resources,
+            InventoryMode::Sell,
+            VirtualKeyCode::A,
+        );
+
+        assert_eq!(gold(&ecs, player), 20);
+        assert!(carried_by(&ecs, player).is_empty());
+        // Trading doesn't take a turn
+        assert_eq!(
+            *resources.get::<TurnState>().unwrap(),
+            TurnState::Inventory {
+                mode: InventoryMode::Sell
+            }
+        );
+    }
+
+    #[test]
+    fn the_price_of_a_potion_gives_nothing_away_until_it_is_identified() {
+        let templates = Templates::load();
+        let mut identification = Identification::new(
+            &mut RandomNumberGenerator::seeded(1),
+            &templates.potion_names(),
+        );
+        let price = |identification: &Identification, name, mode| {
+            trade_price(&templates, identification, name, mode)
+        };
+
+        for mode in [InventoryMode::Buy, InventoryMode::Sell] {
+            assert_eq!(
+                price(&identification, "Potion of Healing", mode),
+                price(&identification, "Potion of Poison", mode)
+            );
+        }
+
+        identification.identify("Potion of Healing");
+        assert_eq!(
+            price(&identification, "Potion of Healing", InventoryMode::Buy),
+            templates.price("Potion of Healing")
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::prelude::*;
+
+use super::inventory::{inventory_list, trade_price};
+
+// Draw the inventory screen on the HUD console: every item that can be picked,
+// under the letter that picks it, by the name the player knows it by.
+// In the shop, each item has its price and the player's gold is shown too.
+#[system]
+#[read_component(Player)]
+#[read_component(Name)]
+#[read_component(Carried)]
+#[read_component(Potion)]
+#[read_component(Food)]
+#[read_component(Teaches)]
+#[read_component(Shopkeeper)]
+#[read_component(Gold)]
+pub fn inventory_menu(
+    ecs: &SubWorld,
+    #[resource] turn_state: &TurnState,
+    #[resource] identification: &Identification,
+    #[resource] templates: &Templates,
+) {
+    let mode = match turn_state {
+        TurnState::Inventory { mode } => *mode,
+        _ => return,
+    };
+    let player = match <Entity>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(player) => *player,
+        None => return,
+    };
+    let items = inventory_list(ecs, player, mode, identification);
+    let trading = matches!(mode, InventoryMode::Buy | InventoryMode::Sell);
+
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(2);
+    let width = 56;
+    let height = items.len().max(1) as i32 + 6;
+    let (x, y) = ((HUD_WIDTH - width) / 2, (HUD_HEIGHT - height) / 2);
+    draw_batch.draw_double_box(
+        Rect::with_size(x, y, width, height),
+        ColorPair::new(WHITE, BLACK),
+    );
+    draw_batch.print_color_centered(y + 1, mode.title(), ColorPair::new(YELLOW, BLACK));
+
+    if items.is_empty() {
+        draw_batch.print_color(
+            Point::new(x + 2, y + 3),
+            "Nothing.",
+            ColorPair::new(GREY, BLACK),
+        );
+    }
+    items.iter().enumerate().for_each(|(i, (_, name))| {
+        let letter = (b'a' + i as u8) as char;
+        let mut text = format!("{}) {}", letter, identification.display_name(name));
+        if trading {
+            text = format!(
+                "{:<36} {:>4} gold",
+                text,
+                trade_price(templates, identification, name, mode)
+            );
+        }
+        draw_batch.print_color(
+            Point::new(x + 2, y + 3 + i as i32),
+            text,
+            ColorPair::new(WHITE, BLACK),
+        );
+    });
+
+    let help = if trading {
+        let gold = <&Gold>::query()
+            .filter(component::<Player>())
+            .iter(ecs)
+            .next()
+            .map_or(0, |gold| gold.0);
+        format!("You have {} gold.  Tab to buy/sell, Esc to leave", gold)
+    } else {
+        "Letter to choose, Esc to close".to_string()
+    };
+    draw_batch.print_color_centered(y + height - 2, help, ColorPair::new(GREY, BLACK));
+
+    // Drawn over the HUD
+    draw_batch.submit(10100).expect("Batch error");
+}
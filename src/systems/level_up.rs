@@ -0,0 +1,105 @@
+use crate::prelude::*;
+
+// How much each level-up reward improves the player
+pub const HEALTH_BONUS: i32 = 3;
+pub const DAMAGE_BONUS: i32 = 1;
+
+// Let the player pick a reward for each level they have gained:
+// 1 raises (and restores) their maximum health, 2 makes them hit harder.
+#[system]
+#[read_component(Player)]
+#[write_component(Health)]
+#[write_component(Damage)]
+#[write_component(Experience)]
+pub fn level_up(
+    ecs: &mut SubWorld,
+    #[resource] key: &Option<VirtualKeyCode>,
+    #[resource] turn_state: &mut TurnState,
+    #[resource] log: &mut GameLog,
+) {
+    if *turn_state != TurnState::LevelUp {
+        return;
+    }
+    let more_health = match key {
+        Some(VirtualKeyCode::Key1) => true,
+        Some(VirtualKeyCode::Key2) => false,
+        _ => return,
+    };
+
+    <(&mut Experience, &mut Health, &mut Damage)>::query()
+        .filter(component::<Player>())
+        .iter_mut(ecs)
+        .for_each(|(experience, health, damage)| {
+            experience.level += 1;
+            experience.pending_level_ups -= 1;
+
+            if more_health {
+                health.max += HEALTH_BONUS;
+                health.current = health.max;
+            } else {
+                damage.0 += DAMAGE_BONUS;
+            }
+            log.add(format!("Welcome to level {}!", experience.level), YELLOW);
+
+            if experience.pending_level_ups < 1 {
+                *turn_state = TurnState::AwaitingInput;
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn experience_queues_a_level_up_for_every_threshold_crossed() {
+        let mut experience = Experience::new();
+
+        experience.gain(9);
+        assert_eq!(experience.pending_level_ups, 0);
+        // Level 2 takes 10 experience and level 3 takes 30
+        experience.gain(21);
+        assert_eq!(experience.pending_level_ups, 2);
+    }
+
+    #[test]
+    fn each_pick_applies_its_reward_until_no_level_ups_are_left() {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        resources.insert(TurnState::LevelUp);
+        resources.insert(GameLog::default());
+        let mut experience = Experience::new();
+        experience.gain(30);
+        let player = ecs.push((
+            Player { map_level: 0 },
+            experience,
+            Health {
+                current: 4,
+                max: 10,
+            },
+            Damage(1),
+        ));
+
+        let mut schedule = Schedule::builder().add_system(level_up_system()).build();
+        for key in [VirtualKeyCode::Key1, VirtualKeyCode::Key2] {
+            assert_eq!(*resources.get::<TurnState>().unwrap(), TurnState::LevelUp);
+            resources.insert(Some(key));
+            schedule.execute(&mut ecs, &mut resources);
+        }
+
+        let entry = ecs.entry(player).unwrap();
+        assert_eq!(entry.get_component::<Experience>().unwrap().level, 3);
+        assert_eq!(
+            *entry.get_component::<Health>().unwrap(),
+            Health {
+                current: 10 + HEALTH_BONUS,
+                max: 10 + HEALTH_BONUS
+            }
+        );
+        assert_eq!(entry.get_component::<Damage>().unwrap().0, 1 + DAMAGE_BONUS);
+        assert_eq!(
+            *resources.get::<TurnState>().unwrap(),
+            TurnState::AwaitingInput
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use crate::prelude::*;
+
+use super::level_up::{DAMAGE_BONUS, HEALTH_BONUS};
+
+// Draw the level-up choice in the middle of the HUD console.
+#[system]
+#[read_component(Player)]
+#[read_component(Health)]
+#[read_component(Damage)]
+#[read_component(Experience)]
+pub fn level_up_menu(ecs: &SubWorld, #[resource] turn_state: &TurnState) {
+    if *turn_state != TurnState::LevelUp {
+        return;
+    }
+    let (experience, health, damage) = match <(&Experience, &Health, &Damage)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(player) => player,
+        None => return,
+    };
+
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(2);
+    let (width, height) = (50, 8);
+    let (x, y) = ((HUD_WIDTH - width) / 2, (HUD_HEIGHT - height) / 2);
+    draw_batch.draw_double_box(
+        Rect::with_size(x, y, width, height),
+        ColorPair::new(YELLOW, BLACK),
+    );
+    draw_batch.print_color_centered(
+        y + 1,
+        format!("You reached level {}!", experience.level + 1),
+        ColorPair::new(YELLOW, BLACK),
+    );
+    draw_batch.print_color(
+        Point::new(x + 2, y + 3),
+        format!(
+            "1. Toughness: max health {} -> {}",
+            health.max,
+            health.max + HEALTH_BONUS
+        ),
+        ColorPair::new(WHITE, BLACK),
+    );
+    draw_batch.print_color(
+        Point::new(x + 2, y + 4),
+        format!(
+            "2. Strength: damage {} -> {}",
+            damage.0,
+            damage.0 + DAMAGE_BONUS
+        ),
+        ColorPair::new(WHITE, BLACK),
+    );
+    draw_batch.print_color_centered(y + 6, "Press 1 or 2 to choose", ColorPair::new(GREY, BLACK));
+
+    // Drawn over the HUD
+    draw_batch.submit(10100).expect("Batch error");
+}
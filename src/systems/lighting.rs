@@ -0,0 +1,23 @@
+use crate::prelude::*;
+
+// Work out how much light falls on each tile from the light sources where they stand now.
+// What creatures can see depends on the light, so when it changes every field of view
+// is recalculated.
+#[system]
+#[read_component(Point)]
+#[read_component(LightSource)]
+#[write_component(FieldOfView)]
+pub fn lighting(ecs: &mut SubWorld, #[resource] map: &Map, #[resource] lighting: &mut Lighting) {
+    let sources: Vec<(Point, LightSource)> = <(&Point, &LightSource)>::query()
+        .iter(ecs)
+        .map(|(pos, source)| (*pos, *source))
+        .collect();
+    let new_lighting = Lighting::compute(map, &sources);
+
+    if new_lighting != *lighting {
+        <&mut FieldOfView>::query()
+            .iter_mut(ecs)
+            .for_each(|fov| fov.is_dirty = true);
+        *lighting = new_lighting;
+    }
+}
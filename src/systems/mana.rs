@@ -0,0 +1,30 @@
+use crate::prelude::*;
+
+// Casters get back a point of mana every turn, up to their maximum.
+#[system]
+#[write_component(Mana)]
+pub fn mana(ecs: &mut SubWorld) {
+    <&mut Mana>::query()
+        .iter_mut(ecs)
+        .for_each(|mana| mana.current = (mana.current + 1).min(mana.max));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mana_comes_back_a_point_a_turn_up_to_the_maximum() {
+        let mut ecs = World::default();
+        let caster = ecs.push((Mana { current: 1, max: 3 },));
+        let mut schedule = Schedule::builder().add_system(mana_system()).build();
+
+        let mut turns = Vec::new();
+        (0..3).for_each(|_| {
+            schedule.execute(&mut ecs, &mut Resources::default());
+            let entry = ecs.entry_ref(caster).unwrap();
+            turns.push(entry.get_component::<Mana>().unwrap().current);
+        });
+        assert_eq!(turns, vec![2, 3, 3]);
+    }
+}
@@ -1,28 +1,51 @@
 use crate::prelude::*;
 
-// System that renders the map
-// This system does not use any components but must request read access to resources map and camera
+// System that renders the map as the player sees it: tiles in view are shaded by the light
+// falling on it, while tiles the player has seen before are drawn from memory in grey
+// It requests read access to the player's field of view and to resources map, camera and lighting
 #[system]
-pub fn map_render(#[resource] map: &Map, #[resource] camera: &Camera) {
+#[read_component(Player)]
+#[read_component(FieldOfView)]
+pub fn map_render(
+    ecs: &SubWorld,
+    #[resource] map: &Map,
+    #[resource] camera: &Camera,
+    #[resource] lighting: &Lighting,
+) {
     // Start a drawing batch
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(0);
 
-    for y in camera.top_y..=camera.bottom_y {
-        for x in camera.left_x..camera.right_x {
-            let pt = Point::new(x, y);
-            let offset = Point::new(camera.left_x, camera.top_y);
-
-            if map.in_bounds(pt) {
-                let idx = map_index(x, y);
-                let glyph = match map.tiles[idx] {
-                    TileType::Floor => to_cp437('.'),
-                    TileType::Wall => to_cp437('#'),
-                };
+    let offset = Point::new(camera.left_x, camera.top_y);
+    let player_fov = <&FieldOfView>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next();
 
-                // Calling the draw batch instead of the context
-                draw_batch.set(pt - offset, ColorPair::new(WHITE, BLACK), glyph);
+    // Only visit the part of the viewport that overlaps the map
+    for y in camera.top_y.max(0)..camera.bottom_y.min(map.height) {
+        for x in camera.left_x.max(0)..camera.right_x.min(map.width) {
+            let pt = Point::new(x, y);
+            let idx = map.map_index(x, y);
+            let visible = player_fov.is_some_and(|fov| fov.visible_tiles.contains(&pt));
+            if !visible && !map.revealed_tiles[idx] {
+                continue;
             }
+            let (glyph, color) = match map.tiles[idx] {
+                TileType::Floor => (to_cp437('.'), WHITE),
+                TileType::Wall => (to_cp437('#'), WHITE),
+                TileType::Door { open: false } => (to_cp437('+'), BROWN1),
+                TileType::Door { open: true } => (to_cp437('\''), BROWN1),
+                TileType::Exit => (to_cp437('>'), WHITE),
+            };
+
+            // Calling the draw batch instead of the context
+            let color = if visible {
+                lighting.shade(color.into(), pt)
+            } else {
+                RGB::named(DARK_GREY)
+            };
+            draw_batch.set(pt - offset, ColorPair::new(color, BLACK), glyph);
         }
     }
 
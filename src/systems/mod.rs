@@ -1,23 +1,139 @@
-mod collisions;
+mod boss;
+mod combat;
+mod end_turn;
 mod entity_render;
+mod equipment;
+mod equipment_menu;
+mod fov;
+mod gold_pickup;
+mod hud;
+mod hunger;
+mod inventory;
+mod inventory_menu;
+mod level_up;
+mod level_up_menu;
+mod lighting;
+mod mana;
 mod map_render;
+mod monster_ai;
+mod movement;
+mod occupancy;
 mod player_input;
-mod random_move;
+mod spellcasting;
+mod spells;
+mod spells_menu;
+mod status_effects;
+mod targeting;
+mod targeting_render;
+mod telegraph_render;
+mod tooltips;
+mod trap_detection;
+mod traps;
 
 use crate::prelude::*;
 
-// This function creates a Legion Schedule — an execution plan for your systems.
-// It follows the builder pattern: Schedule::builder starts the system-building process and build() finishes it.
-pub fn build_schedule() -> Schedule {
+// These functions create Legion Schedules — execution plans for your systems.
+// They follow the builder pattern: Schedule::builder starts the system-building process and build() finishes it.
+// There is one schedule per `TurnState`, so that monsters only act once the player has acted.
+// Rendering lives in a schedule of its own, so the game logic can run without a window.
+pub fn build_input_scheduler() -> Schedule {
     Schedule::builder()
+        // Bring the light and fields of view up to date with last turn's movement
+        // before the player acts
+        .add_system(lighting::lighting_system())
+        .add_system(fov::fov_system())
+        .add_system(occupancy::occupancy_system())
+        .flush()
+        // Targeting and the equipment, inventory and spells screens run first, so the key
+        // that opens them isn't also taken as a key pressed in them
+        .add_system(targeting::targeting_system())
+        .add_system(equipment::equipment_system())
+        .add_system(inventory::inventory_system())
+        .add_system(spells::spells_system())
         .add_system(player_input::player_input_system())
-        .add_system(collisions::collisions_system())
-        // Flush after collision detection ensures any deleted entities are gone bfore rendered
-        // and all systems up to that point have finihsed executing before the next one runs
+        .add_system(level_up::level_up_system())
+        .build()
+}
+
+pub fn build_player_scheduler() -> Schedule {
+    Schedule::builder()
+        .add_system(occupancy::occupancy_system())
+        // Spells first, as their damage is dealt as attacks
+        .add_system(spellcasting::spellcasting_system())
+        .flush()
+        .add_system(combat::combat_system())
+        // Flush after combat ensures any slain entities are gone before anything moves
+        // and all systems up to that point have finished executing before the next one runs
+        .flush()
+        .add_system(movement::movement_system())
         .flush()
+        // Traps sprung by the move, and the attacks they make
+        .add_system(traps::traps_system())
+        .add_system(gold_pickup::gold_pickup_system())
+        .flush()
+        .add_system(combat::combat_system())
+        .add_system(trap_detection::trap_detection_system())
+        .flush()
+        .add_system(end_turn::end_turn_system())
+        .build()
+}
+
+// The monsters' turn also moves the clock on: status effects, hunger and mana are
+// ticked once per turn, after the monsters have acted.
+pub fn build_monster_scheduler() -> Schedule {
+    let mut builder = Schedule::builder();
+    add_monster_actions(&mut builder, true)
+        .add_system(status_effects::status_effects_system())
+        .add_system(hunger::hunger_system())
+        .add_system(mana::mana_system())
+        .flush()
+        .add_system(end_turn::end_turn_system())
+        .build()
+}
+
+// The hasted monsters' extra actions are part of the same turn, so the clock stands still.
+// The boss can't be hasted, so it sits this one out.
+pub fn build_hasted_monster_scheduler() -> Schedule {
+    let mut builder = Schedule::builder();
+    add_monster_actions(&mut builder, false)
+        .add_system(end_turn::end_turn_system())
+        .build()
+}
+
+// The monsters decide what to do, then move and attack.
+fn add_monster_actions(builder: &mut systems::Builder, with_boss: bool) -> &mut systems::Builder {
+    builder
+        .add_system(occupancy::occupancy_system())
+        .add_system(monster_ai::monster_ai_system());
+    if with_boss {
+        builder.add_system(boss::boss_system());
+    }
+    builder
+        .flush()
+        // Minions summoned by the boss only exist once the commands are flushed
+        .add_system(occupancy::occupancy_system())
+        .add_system(combat::combat_system())
+        .flush()
+        .add_system(movement::movement_system())
+        .flush()
+        .add_system(traps::traps_system())
+        .flush()
+        .add_system(combat::combat_system())
+        .flush()
+}
+
+pub fn build_render_scheduler() -> Schedule {
+    Schedule::builder()
         .add_system(map_render::map_render_system())
         .add_system(entity_render::entity_render_system())
-        .add_system(random_move::random_move_system())
+        .add_system(telegraph_render::telegraph_render_system())
+        .add_system(hud::hud_system())
+        .add_system(tooltips::tooltips_system())
+        .add_system(equipment_menu::equipment_menu_system())
+        .add_system(inventory_menu::inventory_menu_system())
+        .add_system(spells_menu::spells_menu_system())
+        .add_system(level_up_menu::level_up_menu_system())
+        .add_system(targeting_render::targeting_render_system())
         .build()
 }
 
@@ -0,0 +1,294 @@
+use crate::prelude::*;
+
+// How many turns a monster keeps searching after losing sight of the player
+const MEMORY_TURNS: i32 = 5;
+// How far away from the player a fleeing monster tries to get
+const FLEE_DISTANCE: f32 = 8.0;
+
+// Decide what every monster does this turn. Each monster first updates its AI state
+// from what it can see, then acts on it:
+//
+// - Idle and Wander monsters that see the player start to Hunt them
+//   (or Flee, if they are badly hurt).
+// - A Hunting monster that loses sight of the player Searches the place it last saw
+//   them for `MEMORY_TURNS` turns, then goes back to wandering.
+// - A Fleeing monster runs until it is `FLEE_DISTANCE` away, then wanders again.
+//
+// Stepping into the player attacks them, stepping into a closed door opens it,
+// and a step into another monster is skipped.
+// Hunting monsters with a ranged weapon shoot instead, whenever they have a clear shot.
+// Confused monsters stagger in a random direction, and on the hasted turn only
+// monsters with an extra action left get to act.
+#[system]
+#[read_component(Point)]
+#[read_component(Player)]
+#[read_component(FieldOfView)]
+#[read_component(Health)]
+#[read_component(Name)]
+#[read_component(RangedWeapon)]
+#[read_component(Inflicts)]
+#[read_component(Damage)]
+#[write_component(StatusEffects)]
+#[write_component(MonsterAi)]
+pub fn monster_ai(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &Map,
+    #[resource] occupancy: &Occupancy,
+    #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] turn_state: &TurnState,
+) {
+    let (player, player_pos) = match <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some((entity, pos)) => (*entity, *pos),
+        None => return,
+    };
+
+    let hasted_turn = *turn_state == TurnState::HastedMonsterTurn;
+    let mut confused = Vec::new();
+    let mut hasted = Vec::new();
+    <(Entity, &mut StatusEffects)>::query()
+        .filter(component::<MonsterAi>())
+        .iter_mut(ecs)
+        .for_each(|(entity, status)| {
+            if status.has(StatusKind::Confusion) {
+                confused.push(*entity);
+            }
+            if hasted_turn && status.extra_actions > 0 {
+                status.extra_actions -= 1;
+                hasted.push(*entity);
+            }
+        });
+
+    let mut monsters = <(
+        Entity,
+        &Point,
+        &FieldOfView,
+        &Health,
+        &Name,
+        Option<&RangedWeapon>,
+        (Option<&Inflicts>, Option<&Damage>),
+        &mut MonsterAi,
+    )>::query();
+
+    monsters
+        .iter_mut(ecs)
+        .filter(|(entity, ..)| !hasted_turn || hasted.contains(*entity))
+        .for_each(
+            |(entity, pos, fov, health, name, ranged, (inflicts, damage), ai)| {
+                let inflicts = inflicts.map(|inflicts| inflicts.0);
+                let damage = damage.map_or(1, |damage| damage.0);
+                let is_confused = confused.contains(entity);
+                let sees_player = fov.visible_tiles.contains(&player_pos);
+                let new_state = next_state(ai.state, sees_player, health, *pos, player_pos);
+                // Trace changes of state, but not the updated details of the same state.
+                // The trace goes to stderr, keeping the headless driver's output parseable.
+                let changed =
+                    std::mem::discriminant(&new_state) != std::mem::discriminant(&ai.state);
+                if changed && cfg!(debug_assertions) {
+                    eprintln!(
+                        "{} {:?} at {:?}: {:?} -> {:?}",
+                        name.0, entity, pos, ai.state, new_state
+                    );
+                }
+                ai.state = new_state;
+
+                if let (AiState::Hunt { .. }, Some(weapon), false) = (ai.state, ranged, is_confused)
+                {
+                    if has_clear_shot(map, occupancy, *pos, player_pos, player, weapon.range) {
+                        commands.push((
+                            (),
+                            WantsToAttack {
+                                attacker: *entity,
+                                victim: player,
+                                damage: weapon.damage,
+                                inflicts,
+                            },
+                        ));
+                        return;
+                    }
+                }
+
+                let destination = match ai.state {
+                    _ if is_confused => Some(*pos + random_step(rng)),
+                    AiState::Idle => None,
+                    AiState::Wander => Some(*pos + random_step(rng)),
+                    AiState::Hunt { .. } => step_towards(map, *pos, player_pos),
+                    AiState::Search { last_known, .. } => step_towards(map, *pos, last_known),
+                    AiState::Flee => step_away(map, *pos, player_pos),
+                };
+
+                if let Some(destination) = destination {
+                    if occupancy.entities_at(destination).contains(&player) {
+                        commands.push((
+                            (),
+                            WantsToAttack {
+                                attacker: *entity,
+                                victim: player,
+                                damage,
+                                inflicts,
+                            },
+                        ));
+                    } else if map.can_reach(*pos, destination)
+                        && !occupancy.is_occupied(destination)
+                    {
+                        commands.push((
+                            (),
+                            WantsToMove {
+                                entity: *entity,
+                                destination,
+                            },
+                        ));
+                    }
+                }
+            },
+        );
+}
+
+fn next_state(
+    state: AiState,
+    sees_player: bool,
+    health: &Health,
+    pos: Point,
+    player_pos: Point,
+) -> AiState {
+    // A single hit point is all some monsters have, so they never flee
+    let badly_hurt = health.max > 1 && health.current * 3 <= health.max;
+    let distance = DistanceAlg::Pythagoras.distance2d(pos, player_pos);
+
+    match state {
+        AiState::Flee if distance < FLEE_DISTANCE => AiState::Flee,
+        AiState::Flee => AiState::Wander,
+        _ if sees_player && badly_hurt => AiState::Flee,
+        _ if sees_player => AiState::Hunt {
+            last_seen: player_pos,
+        },
+        AiState::Hunt { last_seen } => AiState::Search {
+            last_known: last_seen,
+            turns_left: MEMORY_TURNS,
+        },
+        AiState::Search {
+            last_known,
+            turns_left,
+        } if turns_left > 0 && pos != last_known => AiState::Search {
+            last_known,
+            turns_left: turns_left - 1,
+        },
+        AiState::Search { .. } => AiState::Wander,
+        AiState::Idle | AiState::Wander => state,
+    }
+}
+
+// A step in a random cardinal direction.
+fn random_step(rng: &mut RandomNumberGenerator) -> Point {
+    match rng.range(0, 4) {
+        0 => Point::new(-1, 0),
+        1 => Point::new(1, 0),
+        2 => Point::new(0, -1),
+        _ => Point::new(0, 1),
+    }
+}
+
+// Whether a shot from `pos` would reach `target` without hitting a wall or another creature.
+fn has_clear_shot(
+    map: &Map,
+    occupancy: &Occupancy,
+    pos: Point,
+    target_pos: Point,
+    target: Entity,
+    range: i32,
+) -> bool {
+    if DistanceAlg::Pythagoras.distance2d(pos, target_pos) > range as f32 {
+        return false;
+    }
+
+    map.line_of_fire(pos, target_pos).is_some_and(|line| {
+        line.iter().all(|tile| {
+            occupancy
+                .entities_at(*tile)
+                .iter()
+                .all(|occupant| *occupant == target)
+        })
+    })
+}
+
+// The first step on the shortest path from `start` to `target`, if there is one.
+pub fn step_towards(map: &Map, start: Point, target: Point) -> Option<Point> {
+    let path = a_star_search(
+        map.point2d_to_index(start),
+        map.point2d_to_index(target),
+        map,
+    );
+
+    if path.success && path.steps.len() > 1 {
+        Some(map.index_to_point2d(path.steps[1]))
+    } else {
+        None
+    }
+}
+
+// The neighbouring tile that takes us furthest from `threat`, if any gets us further away.
+fn step_away(map: &Map, start: Point, threat: Point) -> Option<Point> {
+    let distance = |pos: Point| DistanceAlg::Pythagoras.distance2d(pos, threat);
+
+    map.get_available_exits(map.point2d_to_index(start))
+        .iter()
+        .map(|(idx, _)| map.index_to_point2d(*idx))
+        .filter(|pos| distance(*pos) > distance(start))
+        .max_by(|a, b| distance(*a).partial_cmp(&distance(*b)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_monster_stepping_into_the_player_attacks_instead_of_moving() {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Map::new(10, 10));
+        resources.insert(Occupancy::default());
+        resources.insert(RandomNumberGenerator::seeded(1));
+        resources.insert(TurnState::MonsterTurn);
+
+        let player_pos = Point::new(6, 5);
+        let player = ecs.push((
+            Player { map_level: 0 },
+            player_pos,
+            Health {
+                current: 10,
+                max: 10,
+            },
+        ));
+        let mut fov = FieldOfView::new(6);
+        fov.visible_tiles.insert(player_pos);
+        let monster = ecs.push((
+            Point::new(5, 5),
+            MonsterAi {
+                state: AiState::Hunt {
+                    last_seen: player_pos,
+                },
+            },
+            fov,
+            Health { current: 3, max: 3 },
+            Name("Goblin".to_string()),
+            StatusEffects::default(),
+        ));
+
+        Schedule::builder()
+            .add_system(super::super::occupancy::occupancy_system())
+            .flush()
+            .add_system(monster_ai_system())
+            .build()
+            .execute(&mut ecs, &mut resources);
+
+        let attacks: Vec<&WantsToAttack> = <&WantsToAttack>::query().iter(&ecs).collect();
+        assert_eq!(attacks.len(), 1);
+        assert_eq!(attacks[0].attacker, monster);
+        assert_eq!(attacks[0].victim, player);
+        assert_eq!(<&WantsToMove>::query().iter(&ecs).count(), 0);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::prelude::*;
+
+// Carry out movement requests in the order they were made. A request for a tile
+// that another creature already occupies (possibly because it moved there earlier
+// this turn) is dropped, so simultaneous moves can never stack creatures.
+// Moving into a closed door opens it instead, which takes the mover's turn.
+// Stepping onto a trap springs it; the `traps` system works out what happens.
+#[system(for_each)]
+#[read_component(Player)]
+#[read_component(Trap)]
+#[write_component(Point)]
+#[write_component(FieldOfView)]
+pub fn movement(
+    entity: &Entity,
+    want_move: &WantsToMove,
+    #[resource] map: &mut Map,
+    #[resource] occupancy: &mut Occupancy,
+    #[resource] camera: &mut Camera,
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+) {
+    let mut opened_door = false;
+    let mut moved = false;
+
+    if let Ok(mut mover) = ecs.entry_mut(want_move.entity) {
+        if let Ok(pos) = mover.get_component_mut::<Point>() {
+            if map.is_closed_door(want_move.destination)
+                && map.can_reach(*pos, want_move.destination)
+            {
+                map.set_door(want_move.destination, true);
+                opened_door = true;
+            } else if map.can_move(*pos, want_move.destination)
+                && !occupancy.is_occupied(want_move.destination)
+            {
+                occupancy.remove(*pos, want_move.entity);
+                occupancy.add(want_move.destination, want_move.entity);
+                *pos = want_move.destination;
+                moved = true;
+
+                if let Ok(fov) = mover.get_component_mut::<FieldOfView>() {
+                    fov.is_dirty = true;
+                }
+                if mover.get_component::<Player>().is_ok() {
+                    camera.on_player_move(want_move.destination, map);
+                }
+            }
+        }
+    }
+
+    if moved {
+        <(Entity, &Point)>::query()
+            .filter(component::<Trap>())
+            .iter(ecs)
+            .filter(|(_, pos)| **pos == want_move.destination)
+            .for_each(|(trap, _)| {
+                commands.push((
+                    (),
+                    TriggerTrap {
+                        trap: *trap,
+                        victim: want_move.entity,
+                    },
+                ));
+            });
+    }
+
+    // Everyone may be able to see through the doorway now
+    if opened_door {
+        <&mut FieldOfView>::query()
+            .iter_mut(ecs)
+            .for_each(|fov| fov.is_dirty = true);
+    }
+
+    // The request has been handled, so remove the message entity
+    commands.remove(*entity);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An open 10x10 map with a creature standing at each of `creatures`.
+    fn setup(creatures: &[Point]) -> (World, Resources, Vec<Entity>) {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        let map = Map::new(10, 10);
+        resources.insert(Camera::new(Point::new(5, 5), 10, 10, &map));
+        resources.insert(map);
+        resources.insert(Occupancy::default());
+
+        let creatures = creatures
+            .iter()
+            .map(|pos| ecs.push((*pos, Health { current: 1, max: 1 })))
+            .collect();
+        (ecs, resources, creatures)
+    }
+
+    // Request each of `moves`, in order, then carry them all out in a single turn.
+    fn run_moves(ecs: &mut World, resources: &mut Resources, moves: &[(Entity, Point)]) {
+        moves.iter().for_each(|(entity, destination)| {
+            ecs.push((
+                (),
+                WantsToMove {
+                    entity: *entity,
+                    destination: *destination,
+                },
+            ));
+        });
+
+        Schedule::builder()
+            .add_system(super::super::occupancy::occupancy_system())
+            .flush()
+            .add_system(movement_system())
+            .build()
+            .execute(ecs, resources);
+    }
+
+    fn position(ecs: &World, entity: Entity) -> Point {
+        *ecs.entry_ref(entity)
+            .unwrap()
+            .get_component::<Point>()
+            .unwrap()
+    }
+
+    #[test]
+    fn only_one_of_two_creatures_moving_to_the_same_tile_gets_there() {
+        let (mut ecs, mut resources, creatures) = setup(&[Point::new(4, 5), Point::new(6, 5)]);
+        let (first, second) = (creatures[0], creatures[1]);
+        let target = Point::new(5, 5);
+
+        run_moves(
+            &mut ecs,
+            &mut resources,
+            &[(first, target), (second, target)],
+        );
+
+        assert_eq!(position(&ecs, first), target);
+        assert_eq!(position(&ecs, second), Point::new(6, 5));
+        let occupancy = resources.get::<Occupancy>().unwrap();
+        assert_eq!(occupancy.entities_at(target), &[first]);
+        // Every request has been dealt with, granted or not
+        assert_eq!(<&WantsToMove>::query().iter(&ecs).count(), 0);
+    }
+
+    #[test]
+    fn a_tile_entered_earlier_in_the_turn_is_taken_but_the_one_left_is_free() {
+        let (mut ecs, mut resources, creatures) =
+            setup(&[Point::new(4, 5), Point::new(5, 4), Point::new(3, 5)]);
+        let (mover, blocked, follower) = (creatures[0], creatures[1], creatures[2]);
+
+        run_moves(
+            &mut ecs,
+            &mut resources,
+            &[
+                (mover, Point::new(5, 5)),
+                (blocked, Point::new(5, 5)),
+                (follower, Point::new(4, 5)),
+            ],
+        );
+
+        assert_eq!(position(&ecs, mover), Point::new(5, 5));
+        assert_eq!(position(&ecs, blocked), Point::new(5, 4));
+        assert_eq!(position(&ecs, follower), Point::new(4, 5));
+    }
+}
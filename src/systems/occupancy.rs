@@ -0,0 +1,16 @@
+use crate::prelude::*;
+
+// Rebuild the occupancy index from the positions of all creatures (entities with health),
+// and of the shopkeeper, who stands in the way without being a creature anyone can fight.
+#[system]
+#[read_component(Point)]
+#[read_component(Health)]
+#[read_component(Shopkeeper)]
+pub fn occupancy(ecs: &SubWorld, #[resource] occupancy: &mut Occupancy) {
+    occupancy.clear();
+
+    <(Entity, &Point)>::query()
+        .filter(component::<Health>() | component::<Shopkeeper>())
+        .iter(ecs)
+        .for_each(|(entity, pos)| occupancy.add(*pos, *entity));
+}
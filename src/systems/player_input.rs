@@ -1,44 +1,377 @@
 use crate::prelude::*;
 
+use super::inventory::inventory_list;
+
+// How far away searching finds hidden traps
+const SEARCH_RADIUS: f32 = 2.0;
+
 // Proc macro #[system] to transforms `player_input` function to `player_input_system`
-// Proc macro #[write_component] requests writable access to a component type, in this case, the `Point` component.
+// Proc macro #[write_component] requests writable access to a component type, in this case, the `Health` component.
 // You must request write access if you intend to change the contents of a component in your system.
 // Proc macro #[read_component] requests read-only access to a component type.
 // You must request read access to use the values stored in a component of this type.
 // `SubWorld` is like a `World` but only sees the components you requested
 // Proc macro #[resource] requests access to types you stored in Legion’s Resource handler.
-// We request read-access on map but mutable access to camera
+// Instead of moving the player directly, we send a message to the movement or combat systems.
 #[system]
-#[write_component(Point)]
+#[allow(clippy::too_many_arguments)]
+#[read_component(Point)]
+#[write_component(Health)]
+#[write_component(FieldOfView)]
 #[read_component(Player)]
+#[read_component(Enemy)]
+#[read_component(Shopkeeper)]
+#[read_component(Item)]
+#[read_component(Carried)]
+#[read_component(Name)]
+#[read_component(RangedWeapon)]
+#[read_component(Thrown)]
+#[read_component(Trap)]
+#[read_component(Hidden)]
+#[read_component(Potion)]
+#[read_component(Damage)]
+#[read_component(Hunger)]
+#[read_component(Food)]
+#[read_component(Teaches)]
+#[read_component(KnownSpells)]
+#[write_component(StatusEffects)]
 pub fn player_input(
     ecs: &mut SubWorld,
-    #[resource] map: &Map,
+    commands: &mut CommandBuffer,
     #[resource] key: &Option<VirtualKeyCode>,
-    #[resource] camera: &mut Camera,
+    #[resource] bindings: &KeyBindings,
+    #[resource] map: &mut Map,
+    #[resource] occupancy: &Occupancy,
+    #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] identification: &Identification,
+    #[resource] turn_state: &mut TurnState,
+    #[resource] log: &mut GameLog,
 ) {
-    if let Some(key) = key {
-        let delta = match key {
-            VirtualKeyCode::Left => Point::new(-1, 0),
-            VirtualKeyCode::Right => Point::new(1, 0),
-            VirtualKeyCode::Up => Point::new(0, -1),
-            VirtualKeyCode::Down => Point::new(0, 1),
-            _ => Point::new(0, 0),
-        };
-
-        if delta.x != 0 || delta.y != 0 {
+    // While targeting, keys steer the cursor instead (see the `targeting` system)
+    if *turn_state != TurnState::AwaitingInput {
+        return;
+    }
+
+    // Keys are translated into actions through the (rebindable) key bindings
+    if let Some(action) = key.and_then(|key| bindings.action(key)) {
+        if let Some(delta) = action.delta() {
             // Queries list one or more components, and return references—mutable if you use &mut to each instance of that component type.
             // Legion queries include a filter() function to further refine the set of components required for a query to match an entity.
             // Filter specifies that only entities with a Point component and a Player tag component should be included in the query.
-            let mut players = <&mut Point>::query().filter(component::<Player>());
-
-            players.iter_mut(ecs).for_each(|pos| {
-                let destination = *pos + delta;
-                if map.can_enter_tile(destination) {
-                    *pos = destination;
-                    camera.on_player_move(destination);
-                }
-            })
+            let (player, pos, confused, damage) =
+                <(Entity, &Point, &StatusEffects, &Damage)>::query()
+                    .filter(component::<Player>())
+                    .iter(ecs)
+                    .map(|(entity, pos, status, damage)| {
+                        (*entity, *pos, status.has(StatusKind::Confusion), damage.0)
+                    })
+                    .next()
+                    .unwrap();
+
+            // A confused player only goes where they meant to half of the time
+            let delta = if confused && rng.range(0, 2) == 0 {
+                log.add("You stumble around in confusion.", MAGENTA);
+                Point::new(rng.range(-1, 2), rng.range(-1, 2))
+            } else {
+                delta
+            };
+            let destination = pos + delta;
+
+            // Bumping into the shopkeeper opens the shop, which doesn't take a turn
+            let shopkeeper = occupancy.entities_at(destination).iter().any(|occupant| {
+                ecs.entry_ref(*occupant)
+                    .is_ok_and(|occupant| occupant.get_component::<Shopkeeper>().is_ok())
+            });
+            if shopkeeper {
+                *turn_state = TurnState::Inventory {
+                    mode: InventoryMode::Buy,
+                };
+                return;
+            }
+
+            // Bumping into an enemy attacks it, otherwise try to move there
+            let enemy = occupancy.entities_at(destination).iter().find(|occupant| {
+                ecs.entry_ref(**occupant)
+                    .is_ok_and(|occupant| occupant.get_component::<Enemy>().is_ok())
+            });
+
+            if let Some(enemy) = enemy {
+                commands.push((
+                    (),
+                    WantsToAttack {
+                        attacker: player,
+                        victim: *enemy,
+                        damage,
+                        inflicts: None,
+                    },
+                ));
+            } else {
+                commands.push((
+                    (),
+                    WantsToMove {
+                        entity: player,
+                        destination,
+                    },
+                ));
+            }
+        } else if action == Action::Wait {
+            rest(ecs, log);
+        } else if action == Action::PickUp {
+            if !pick_up(ecs, commands, identification, log) {
+                return;
+            }
+        } else if let Some(mode) = inventory_mode(action) {
+            // Choosing an item doesn't take a turn; doing something with it does
+            if has_items(ecs, mode, identification, log) {
+                *turn_state = TurnState::Inventory { mode };
+            }
+            return;
+        } else if action == Action::Cast {
+            // Choosing a spell doesn't take a turn; casting it does
+            let knows_spells = <&KnownSpells>::query()
+                .filter(component::<Player>())
+                .iter(ecs)
+                .any(|known| !known.0.is_empty());
+            if knows_spells {
+                *turn_state = TurnState::Spells;
+            } else {
+                log.add("You don't know any spells.", GREY);
+            }
+            return;
+        } else if action == Action::Equipment {
+            // Looking through the equipment doesn't take a turn; changing it does
+            *turn_state = TurnState::Equipment;
+            return;
+        } else if action == Action::Search {
+            search(ecs, commands, log);
+        } else if action == Action::CloseDoor {
+            if !close_doors(ecs, map, log) {
+                return;
+            }
+        } else if action == Action::Fire {
+            // Aiming doesn't take a turn; firing does, once the target is confirmed
+            if let Some(cursor) = start_targeting(ecs, log) {
+                *turn_state = TurnState::Targeting { cursor };
+            }
+            return;
+        } else {
+            // Other actions are handled by the front-end and don't take a turn
+            return;
         }
+
+        // Any action ends the player's turn, even if they bumped into a wall
+        *turn_state = TurnState::PlayerTurn;
     }
 }
+
+// Waiting a turn heals the player a little, but only while no enemy is in sight
+// and they aren't weak with hunger.
+fn rest(ecs: &mut SubWorld, log: &mut GameLog) {
+    let enemy_positions: Vec<Point> = <&Point>::query()
+        .filter(component::<Enemy>())
+        .iter(ecs)
+        .copied()
+        .collect();
+    let too_hungry = <&Hunger>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .any(|hunger| hunger.state() <= HungerState::Weak);
+
+    <(&FieldOfView, &mut Health)>::query()
+        .filter(component::<Player>())
+        .iter_mut(ecs)
+        .filter(|(fov, _)| {
+            !enemy_positions
+                .iter()
+                .any(|pos| fov.visible_tiles.contains(pos))
+        })
+        .filter(|(_, health)| health.current < health.max)
+        .for_each(|(_, health)| {
+            if too_hungry {
+                log.add("You are too hungry to recover.", ORANGE);
+            } else {
+                health.current += 1;
+                log.add("You rest and recover 1 health.", GREEN);
+            }
+        });
+}
+
+// Pick up the item the player is standing on. Returns whether there was one.
+fn pick_up(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    identification: &Identification,
+    log: &mut GameLog,
+) -> bool {
+    let (player, player_pos) = <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .map(|(entity, pos)| (*entity, *pos))
+        .next()
+        .unwrap();
+
+    let item = <(Entity, &Point, &Name)>::query()
+        .filter(component::<Item>())
+        .iter(ecs)
+        .find(|(_, pos, _)| **pos == player_pos);
+
+    match item {
+        Some((item, _, name)) => {
+            commands.remove_component::<Point>(*item);
+            commands.add_component(*item, Carried(player));
+            log.add(
+                format!("You pick up the {}.", identification.display_name(&name.0)),
+                WHITE,
+            );
+            true
+        }
+        None => {
+            log.add("There is nothing here to pick up.", GREY);
+            false
+        }
+    }
+}
+
+// The inventory screen an action opens, if any.
+fn inventory_mode(action: Action) -> Option<InventoryMode> {
+    match action {
+        Action::Quaff => Some(InventoryMode::Quaff),
+        Action::Eat => Some(InventoryMode::Eat),
+        Action::Read => Some(InventoryMode::Read),
+        Action::Drop => Some(InventoryMode::Drop),
+        Action::Throw => Some(InventoryMode::Throw),
+        _ => None,
+    }
+}
+
+// Whether the player has anything to pick on the inventory screen in `mode`,
+// explaining why not if they don't.
+fn has_items(
+    ecs: &SubWorld,
+    mode: InventoryMode,
+    identification: &Identification,
+    log: &mut GameLog,
+) -> bool {
+    let player = <Entity>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .copied()
+        .next()
+        .unwrap();
+
+    if inventory_list(ecs, player, mode, identification).is_empty() {
+        match mode {
+            InventoryMode::Quaff => log.add("You have nothing to drink.", GREY),
+            InventoryMode::Eat => log.add("You have nothing to eat.", GREY),
+            InventoryMode::Read => log.add("You have nothing to read.", GREY),
+            _ => log.add("You aren't carrying anything.", GREY),
+        }
+        return false;
+    }
+    true
+}
+
+// Spend a turn searching the area, revealing every hidden trap within
+// `SEARCH_RADIUS` tiles that the player can see.
+fn search(ecs: &SubWorld, commands: &mut CommandBuffer, log: &mut GameLog) {
+    let (player_pos, fov) = <(&Point, &FieldOfView)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+        .unwrap();
+
+    log.add("You search the area.", WHITE);
+    <(Entity, &Point, &Name)>::query()
+        .filter(component::<Trap>() & component::<Hidden>())
+        .iter(ecs)
+        .filter(|(_, pos, _)| {
+            fov.visible_tiles.contains(pos)
+                && DistanceAlg::Chebyshev.distance2d(*player_pos, **pos) <= SEARCH_RADIUS
+        })
+        .for_each(|(trap, _, name)| {
+            commands.remove_component::<Hidden>(*trap);
+            log.add(format!("You find {}.", a_or_an(&name.0)), YELLOW);
+        });
+}
+
+// Close the open doors next to the player, unless something is standing in them.
+// Returns whether any door was closed.
+fn close_doors(ecs: &mut SubWorld, map: &mut Map, log: &mut GameLog) -> bool {
+    let player_pos = <&Point>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .copied()
+        .next()
+        .unwrap();
+    let blocked: Vec<Point> = <&Point>::query().iter(ecs).copied().collect();
+
+    let doors: Vec<Point> = (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| player_pos + Point::new(dx, dy)))
+        .filter(|pos| map.is_open_door(*pos) && !blocked.contains(pos))
+        .collect();
+
+    if doors.is_empty() {
+        log.add("There is no open door next to you.", GREY);
+        return false;
+    }
+
+    doors.iter().for_each(|pos| map.set_door(*pos, false));
+    log.add("You close the door.", WHITE);
+
+    // The closed door may hide what was behind it
+    <&mut FieldOfView>::query()
+        .iter_mut(ecs)
+        .for_each(|fov| fov.is_dirty = true);
+    true
+}
+
+// Pick where the targeting cursor starts: on the nearest visible enemy, or on the player.
+// Returns `None` if the player has nothing to shoot with.
+fn start_targeting(ecs: &SubWorld, log: &mut GameLog) -> Option<Point> {
+    let player = <Entity>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .copied()
+        .next()?;
+
+    if ranged_weapon(ecs, player).is_none() {
+        log.add("You have nothing to shoot with.", GREY);
+        return None;
+    }
+
+    let (player_pos, fov) = <(&Point, &FieldOfView)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()?;
+
+    let nearest = visible_enemies(ecs, *player_pos, fov).into_iter().next();
+    Some(nearest.unwrap_or(*player_pos))
+}
+
+// The ranged weapon `owner` would use: a weapon they keep (like a bow) rather than
+// one they throw away, if they carry both.
+pub fn ranged_weapon(ecs: &SubWorld, owner: Entity) -> Option<Entity> {
+    let mut weapons: Vec<(Entity, bool)> = <(Entity, &Carried, Option<&Thrown>)>::query()
+        .filter(component::<RangedWeapon>())
+        .iter(ecs)
+        .filter(|(_, carried, _)| carried.0 == owner)
+        .map(|(entity, _, thrown)| (*entity, thrown.is_some()))
+        .collect();
+
+    weapons.sort_by_key(|(_, thrown)| *thrown);
+    weapons.first().map(|(entity, _)| *entity)
+}
+
+// The positions of the enemies in `fov`, nearest first.
+pub fn visible_enemies(ecs: &SubWorld, from: Point, fov: &FieldOfView) -> Vec<Point> {
+    let mut enemies: Vec<Point> = <&Point>::query()
+        .filter(component::<Enemy>())
+        .iter(ecs)
+        .filter(|pos| fov.visible_tiles.contains(pos))
+        .copied()
+        .collect();
+
+    let distance = |pos: &Point| DistanceAlg::Pythagoras.distance2d(from, *pos);
+    enemies.sort_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap());
+    enemies
+}
@@ -0,0 +1,275 @@
+use crate::prelude::*;
+
+use super::targeting::first_creature;
+
+// Resolve spells: the caster pays the spell's mana, then it takes effect. Healing spells
+// mend the caster and can cure them too, and blinking moves the caster to the target in
+// an instant. Damaging spells hit the first creature in their path, or burst where they
+// strike and hit everything within their radius but the caster. Their damage is dealt
+// by the combat system, like that of any other attack.
+#[system]
+#[allow(clippy::too_many_arguments)]
+#[read_component(WantsToCast)]
+#[read_component(Player)]
+#[write_component(Point)]
+#[write_component(FieldOfView)]
+#[write_component(Mana)]
+#[write_component(Health)]
+#[write_component(StatusEffects)]
+pub fn spellcasting(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &Map,
+    #[resource] occupancy: &mut Occupancy,
+    #[resource] camera: &mut Camera,
+    #[resource] templates: &Templates,
+    #[resource] log: &mut GameLog,
+) {
+    let casts: Vec<(Entity, WantsToCast)> = <(Entity, &WantsToCast)>::query()
+        .iter(ecs)
+        .map(|(message, cast)| (*message, cast.clone()))
+        .collect();
+
+    casts.iter().for_each(|(message, cast)| {
+        commands.remove(*message);
+        let spell = match templates.spell(&cast.spell) {
+            Some(spell) => spell,
+            None => return,
+        };
+        let caster_pos = match ecs.entry_ref(cast.caster) {
+            Ok(caster) => match caster.get_component::<Point>() {
+                Ok(pos) => *pos,
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+
+        if let Ok(mut caster) = ecs.entry_mut(cast.caster) {
+            if let Ok(mana) = caster.get_component_mut::<Mana>() {
+                mana.current -= spell.mana;
+            }
+        }
+        log.add(format!("You cast {}.", spell.name), CYAN);
+
+        if let Some(healing) = spell.healing {
+            heal(ecs, log, cast.caster, healing, spell.cures);
+        }
+        if spell.blink {
+            blink(ecs, map, occupancy, camera, log, cast.caster, cast.target);
+        }
+
+        let damage = match spell.damage {
+            Some(damage) => damage,
+            None => return,
+        };
+        let line = map
+            .line_of_fire(caster_pos, cast.target)
+            .unwrap_or_default();
+        let hit = first_creature(ecs, occupancy, &line);
+        let victims: Vec<Entity> = match spell.radius {
+            Some(radius) => {
+                let impact = hit.map_or(cast.target, |(pos, _)| pos);
+                log.add(format!("The {} bursts!", spell.name), ORANGE);
+                <(Entity, &Point)>::query()
+                    .filter(component::<Health>())
+                    .iter(ecs)
+                    .filter(|(entity, pos)| {
+                        **entity != cast.caster
+                            && DistanceAlg::Chebyshev.distance2d(impact, **pos) <= radius as f32
+                    })
+                    .map(|(entity, _)| *entity)
+                    .collect()
+            }
+            None => hit.map(|(_, victim)| victim).into_iter().collect(),
+        };
+
+        victims.into_iter().for_each(|victim| {
+            commands.push((
+                (),
+                WantsToAttack {
+                    attacker: cast.caster,
+                    victim,
+                    damage,
+                    inflicts: spell.inflicts,
+                },
+            ));
+        });
+    });
+}
+
+fn heal(
+    ecs: &mut SubWorld,
+    log: &mut GameLog,
+    caster: Entity,
+    healing: i32,
+    cures: Option<StatusKind>,
+) {
+    let mut caster = match ecs.entry_mut(caster) {
+        Ok(caster) => caster,
+        Err(_) => return,
+    };
+    if let Ok(health) = caster.get_component_mut::<Health>() {
+        health.current = (health.current + healing).min(health.max);
+        log.add("You feel better.", GREEN);
+    }
+    if let (Some(kind), Ok(status)) = (cures, caster.get_component_mut::<StatusEffects>()) {
+        if status.has(kind) {
+            status.effects.retain(|effect| effect.kind != kind);
+            log.add(
+                format!("You are no longer {}.", kind.name().to_lowercase()),
+                GREEN,
+            );
+        }
+    }
+}
+
+// Move the caster to `target` in an instant, if nothing has taken the spot since
+// it was chosen.
+fn blink(
+    ecs: &mut SubWorld,
+    map: &Map,
+    occupancy: &mut Occupancy,
+    camera: &mut Camera,
+    log: &mut GameLog,
+    caster: Entity,
+    target: Point,
+) {
+    if !map.can_enter_tile(target) || occupancy.is_occupied(target) {
+        log.add("The spell fizzles.", GREY);
+        return;
+    }
+    if let Ok(mut caster_entry) = ecs.entry_mut(caster) {
+        if let Ok(pos) = caster_entry.get_component_mut::<Point>() {
+            occupancy.remove(*pos, caster);
+            occupancy.add(target, caster);
+            *pos = target;
+        }
+        if let Ok(fov) = caster_entry.get_component_mut::<FieldOfView>() {
+            fov.is_dirty = true;
+        }
+        if caster_entry.get_component::<Player>().is_ok() {
+            camera.on_player_move(target, map);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (World, Resources) {
+        let mut resources = Resources::default();
+        let map = Map::new(20, 10);
+        resources.insert(Camera::new(Point::new(1, 1), 20, 10, &map));
+        resources.insert(map);
+        resources.insert(Occupancy::default());
+        resources.insert(Templates::load());
+        resources.insert(GameLog::default());
+        (World::default(), resources)
+    }
+
+    fn spawn_creature(ecs: &mut World, resources: &mut Resources, pos: Point) -> Entity {
+        let entity = ecs.push((Enemy, pos, Health { current: 5, max: 5 }));
+        resources.get_mut::<Occupancy>().unwrap().add(pos, entity);
+        entity
+    }
+
+    fn cast(
+        ecs: &mut World,
+        resources: &mut Resources,
+        caster: Entity,
+        spell: &str,
+        target: Point,
+    ) {
+        ecs.push((
+            (),
+            WantsToCast {
+                caster,
+                spell: spell.to_string(),
+                target,
+            },
+        ));
+        Schedule::builder()
+            .add_system(spellcasting_system())
+            .build()
+            .execute(ecs, resources);
+    }
+
+    fn mana(ecs: &World, caster: Entity) -> i32 {
+        ecs.entry_ref(caster)
+            .unwrap()
+            .get_component::<Mana>()
+            .unwrap()
+            .current
+    }
+
+    #[test]
+    fn a_fireball_costs_its_mana_and_hits_everything_around_where_it_bursts() {
+        let (mut ecs, mut resources) = setup();
+        let caster = ecs.push((
+            Player { map_level: 0 },
+            Point::new(1, 1),
+            Mana {
+                current: 12,
+                max: 12,
+            },
+        ));
+        let first = spawn_creature(&mut ecs, &mut resources, Point::new(5, 1));
+        let beside = spawn_creature(&mut ecs, &mut resources, Point::new(6, 2));
+        let far = spawn_creature(&mut ecs, &mut resources, Point::new(8, 1));
+
+        cast(
+            &mut ecs,
+            &mut resources,
+            caster,
+            "Fireball",
+            Point::new(8, 1),
+        );
+
+        assert_eq!(mana(&ecs, caster), 4);
+        let victims: Vec<Entity> = <&WantsToAttack>::query()
+            .iter(&ecs)
+            .map(|attack| {
+                assert_eq!(attack.damage, 3);
+                attack.victim
+            })
+            .collect();
+        assert!(victims.contains(&first));
+        assert!(victims.contains(&beside));
+        assert!(!victims.contains(&far));
+    }
+
+    #[test]
+    fn healing_mends_the_caster_and_cures_their_poison() {
+        let (mut ecs, mut resources) = setup();
+        let caster = ecs.push((
+            Player { map_level: 0 },
+            Point::new(1, 1),
+            Mana {
+                current: 5,
+                max: 12,
+            },
+            Health {
+                current: 2,
+                max: 10,
+            },
+            StatusEffects {
+                effects: vec![StatusEffect {
+                    kind: StatusKind::Poison,
+                    turns_remaining: 3,
+                }],
+                extra_actions: 0,
+            },
+        ));
+
+        cast(&mut ecs, &mut resources, caster, "Heal", Point::new(1, 1));
+
+        assert_eq!(mana(&ecs, caster), 0);
+        let entry = ecs.entry_ref(caster).unwrap();
+        assert_eq!(entry.get_component::<Health>().unwrap().current, 7);
+        assert!(!entry
+            .get_component::<StatusEffects>()
+            .unwrap()
+            .has(StatusKind::Poison));
+    }
+}
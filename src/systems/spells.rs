@@ -0,0 +1,152 @@
+use crate::prelude::*;
+
+use super::player_input::visible_enemies;
+
+// The spells `player` knows, in the order the spells screen lists them.
+pub fn spell_list<'a>(
+    ecs: &SubWorld,
+    player: Entity,
+    templates: &'a Templates,
+) -> Vec<&'a SpellTemplate> {
+    ecs.entry_ref(player)
+        .ok()
+        .and_then(|player| player.get_component::<KnownSpells>().ok().cloned())
+        .map_or_else(Vec::new, |known| {
+            known
+                .0
+                .iter()
+                .filter_map(|name| templates.spell(name))
+                .collect()
+        })
+}
+
+// Handle keys on the spells screen. Each known spell is listed under a letter:
+// picking one the player has the mana for casts it on themselves, or starts aiming it
+// if it has a range. Casting takes a turn. Escape closes the screen; the cast key doesn't,
+// as it may be the letter of a spell.
+#[system]
+#[read_component(Player)]
+#[read_component(Point)]
+#[read_component(Enemy)]
+#[read_component(FieldOfView)]
+#[read_component(Mana)]
+#[read_component(KnownSpells)]
+#[allow(clippy::too_many_arguments)]
+pub fn spells(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] key: &mut Option<VirtualKeyCode>,
+    #[resource] bindings: &KeyBindings,
+    #[resource] templates: &Templates,
+    #[resource] turn_state: &mut TurnState,
+    #[resource] log: &mut GameLog,
+) {
+    if *turn_state != TurnState::Spells {
+        return;
+    }
+    let pressed = match key {
+        Some(key) => *key,
+        None => return,
+    };
+    if bindings.action(pressed) == Some(Action::Cancel) {
+        *turn_state = TurnState::AwaitingInput;
+        // The key is used up, so `player_input` doesn't open the screen straight back up
+        *key = None;
+        return;
+    }
+
+    let (player, player_pos, mana, fov) = match <(Entity, &Point, &Mana, &FieldOfView)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some((entity, pos, mana, fov)) => (*entity, *pos, *mana, fov),
+        None => return,
+    };
+    let spells = spell_list(ecs, player, templates);
+    let spell = match usize::try_from(letter_to_option(pressed))
+        .ok()
+        .and_then(|index| spells.get(index))
+    {
+        Some(spell) => *spell,
+        None => return,
+    };
+
+    if mana.current < spell.mana {
+        log.add(
+            format!("You don't have enough mana to cast {}.", spell.name),
+            GREY,
+        );
+        return;
+    }
+
+    if spell.range.is_some() {
+        // Aiming doesn't take a turn; casting does, once the target is confirmed
+        commands.add_component(player, Casting(spell.name.clone()));
+        let cursor = if spell.blink {
+            player_pos
+        } else {
+            visible_enemies(ecs, player_pos, fov)
+                .into_iter()
+                .next()
+                .unwrap_or(player_pos)
+        };
+        *turn_state = TurnState::Targeting { cursor };
+        return;
+    }
+
+    commands.push((
+        (),
+        WantsToCast {
+            caster: player,
+            spell: spell.name.clone(),
+            target: player_pos,
+        },
+    ));
+    *turn_state = TurnState::PlayerTurn;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_spell_can_only_be_cast_with_the_mana_for_it() {
+        let mut resources = Resources::default();
+        resources.insert(KeyBindings::default());
+        resources.insert(Templates::load());
+        resources.insert(GameLog::default());
+        let mut ecs = World::default();
+        let player = ecs.push((
+            Player { map_level: 0 },
+            Point::new(1, 1),
+            FieldOfView::new(8),
+            Mana {
+                current: 4,
+                max: 12,
+            },
+            KnownSpells(vec!["Magic Missile".to_string(), "Heal".to_string()]),
+        ));
+        let mut pick = |ecs: &mut World, key| {
+            resources.insert(TurnState::Spells);
+            resources.insert(Some(key));
+            Schedule::builder()
+                .add_system(spells_system())
+                .build()
+                .execute(ecs, &mut resources);
+            *resources.get::<TurnState>().unwrap()
+        };
+
+        // Heal, under B, costs 5
+        assert_eq!(pick(&mut ecs, VirtualKeyCode::B), TurnState::Spells);
+        assert_eq!(<&WantsToCast>::query().iter(&ecs).count(), 0);
+
+        // Magic Missile, under A, costs 3 and is aimed first
+        assert!(matches!(
+            pick(&mut ecs, VirtualKeyCode::A),
+            TurnState::Targeting { .. }
+        ));
+        let entry = ecs.entry_ref(player).unwrap();
+        assert_eq!(entry.get_component::<Casting>().unwrap().0, "Magic Missile");
+    }
+}
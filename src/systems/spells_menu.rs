@@ -0,0 +1,65 @@
+use crate::prelude::*;
+
+use super::spells::spell_list;
+
+// Draw the spells screen on the HUD console: every spell the player knows under the
+// letter that casts it, with its cost. Spells they can't afford yet are greyed out.
+#[system]
+#[read_component(Player)]
+#[read_component(Mana)]
+#[read_component(KnownSpells)]
+pub fn spells_menu(
+    ecs: &SubWorld,
+    #[resource] turn_state: &TurnState,
+    #[resource] templates: &Templates,
+) {
+    if *turn_state != TurnState::Spells {
+        return;
+    }
+    let (player, mana) = match <(Entity, &Mana)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some((player, mana)) => (*player, *mana),
+        None => return,
+    };
+    let spells = spell_list(ecs, player, templates);
+
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(2);
+    let width = 48;
+    let height = spells.len().max(1) as i32 + 6;
+    let (x, y) = ((HUD_WIDTH - width) / 2, (HUD_HEIGHT - height) / 2);
+    draw_batch.draw_double_box(
+        Rect::with_size(x, y, width, height),
+        ColorPair::new(WHITE, BLACK),
+    );
+    draw_batch.print_color_centered(y + 1, "Cast which spell?", ColorPair::new(YELLOW, BLACK));
+
+    spells.iter().enumerate().for_each(|(i, spell)| {
+        let letter = (b'a' + i as u8) as char;
+        let color = if spell.mana <= mana.current {
+            WHITE
+        } else {
+            GREY
+        };
+        draw_batch.print_color(
+            Point::new(x + 2, y + 3 + i as i32),
+            format!("{}) {:<20} {:>3} mana", letter, spell.name, spell.mana),
+            ColorPair::new(color, BLACK),
+        );
+    });
+
+    draw_batch.print_color_centered(
+        y + height - 2,
+        format!(
+            "Mana: {}/{}   Letter to cast, Esc to close",
+            mana.current, mana.max
+        ),
+        ColorPair::new(GREY, BLACK),
+    );
+
+    // Drawn over the HUD
+    draw_batch.submit(10100).expect("Batch error");
+}
@@ -0,0 +1,201 @@
+use crate::prelude::*;
+
+use super::combat::monster_died;
+
+// Tick the status effects of every creature once per turn, after the monsters have moved.
+// Poison deals a point of damage, haste grants an extra action for the coming turn,
+// and effects that have run their course wear off. Monsters that die of poison die
+// as they would in combat, dropping their loot, and the player is credited with the kill.
+#[system]
+#[read_component(Player)]
+#[read_component(Name)]
+#[read_component(Point)]
+#[read_component(XpValue)]
+#[read_component(Gold)]
+#[read_component(Boss)]
+#[write_component(Health)]
+#[write_component(StatusEffects)]
+#[write_component(Experience)]
+pub fn status_effects(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] occupancy: &mut Occupancy,
+    #[resource] log: &mut GameLog,
+    #[resource] stats: &mut RunStats,
+) {
+    let player = match <Entity>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(player) => *player,
+        None => return,
+    };
+
+    let mut poisoned_monsters = Vec::new();
+    <(Entity, &mut StatusEffects, &mut Health, Option<&Player>)>::query()
+        .iter_mut(ecs)
+        .filter(|(_, status, ..)| !status.effects.is_empty())
+        .for_each(|(entity, status, health, player)| {
+            let is_player = player.is_some();
+
+            if status.has(StatusKind::Poison) && health.current > 0 {
+                health.current -= 1;
+                if is_player {
+                    log.add("The poison hurts you for 1.", RED);
+                }
+                if health.current < 1 {
+                    if is_player {
+                        log.add("You die...", RED);
+                        stats.died("Died of poison");
+                    } else {
+                        poisoned_monsters.push(*entity);
+                    }
+                }
+            }
+
+            status
+                .effects
+                .iter_mut()
+                .for_each(|effect| effect.turns_remaining -= 1);
+            status.effects.retain(|effect| {
+                let active = effect.turns_remaining > 0;
+                if !active && is_player {
+                    log.add(
+                        format!("You are no longer {}.", effect.kind.name().to_lowercase()),
+                        GREY,
+                    );
+                }
+                active
+            });
+
+            status.extra_actions = if status.has(StatusKind::Haste) { 1 } else { 0 };
+        });
+
+    poisoned_monsters.iter().for_each(|monster| {
+        monster_died(
+            ecs,
+            commands,
+            occupancy,
+            log,
+            stats,
+            *monster,
+            player,
+            "dies of poison",
+        )
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(kind: StatusKind, turns_remaining: i32) -> StatusEffect {
+        StatusEffect {
+            kind,
+            turns_remaining,
+        }
+    }
+
+    fn setup() -> (World, Resources) {
+        let mut resources = Resources::default();
+        resources.insert(Occupancy::default());
+        resources.insert(GameLog::default());
+        resources.insert(RunStats::default());
+        (World::default(), resources)
+    }
+
+    fn tick(ecs: &mut World, resources: &mut Resources) {
+        Schedule::builder()
+            .add_system(status_effects_system())
+            .build()
+            .execute(ecs, resources);
+    }
+
+    #[test]
+    fn poison_hurts_every_turn_until_it_wears_off() {
+        let (mut ecs, mut resources) = setup();
+        let player = ecs.push((
+            Player { map_level: 0 },
+            Health {
+                current: 10,
+                max: 10,
+            },
+            StatusEffects {
+                effects: vec![effect(StatusKind::Poison, 2)],
+                extra_actions: 0,
+            },
+        ));
+
+        (0..3).for_each(|_| tick(&mut ecs, &mut resources));
+
+        let entry = ecs.entry(player).unwrap();
+        assert_eq!(entry.get_component::<Health>().unwrap().current, 8);
+        assert!(entry
+            .get_component::<StatusEffects>()
+            .unwrap()
+            .effects
+            .is_empty());
+    }
+
+    #[test]
+    fn haste_grants_an_extra_action_while_it_lasts() {
+        let (mut ecs, mut resources) = setup();
+        ecs.push((Player { map_level: 0 }, Experience::new()));
+        let monster = ecs.push((
+            Health { current: 3, max: 3 },
+            StatusEffects {
+                effects: vec![effect(StatusKind::Haste, 2)],
+                extra_actions: 0,
+            },
+        ));
+        let extra_actions = |ecs: &mut World| {
+            ecs.entry(monster)
+                .unwrap()
+                .get_component::<StatusEffects>()
+                .unwrap()
+                .extra_actions
+        };
+
+        tick(&mut ecs, &mut resources);
+        assert_eq!(extra_actions(&mut ecs), 1);
+        tick(&mut ecs, &mut resources);
+        assert_eq!(extra_actions(&mut ecs), 0);
+    }
+
+    #[test]
+    fn a_monster_killed_by_poison_is_removed_and_credited_to_the_player() {
+        let (mut ecs, mut resources) = setup();
+        let player = ecs.push((Player { map_level: 0 }, Experience::new()));
+        let monster = ecs.push((
+            XpValue(4),
+            Name("Goblin".to_string()),
+            Point::new(3, 3),
+            Health { current: 1, max: 3 },
+            StatusEffects {
+                effects: vec![effect(StatusKind::Poison, 5)],
+                extra_actions: 0,
+            },
+        ));
+        resources
+            .get_mut::<Occupancy>()
+            .unwrap()
+            .add(Point::new(3, 3), monster);
+
+        tick(&mut ecs, &mut resources);
+
+        assert!(ecs.entry(monster).is_none());
+        assert!(!resources
+            .get::<Occupancy>()
+            .unwrap()
+            .is_occupied(Point::new(3, 3)));
+        let experience = ecs.entry(player).unwrap();
+        assert_eq!(experience.get_component::<Experience>().unwrap().xp, 4);
+        assert_eq!(resources.get::<RunStats>().unwrap().total_kills(), 1);
+        let log = resources.get::<GameLog>().unwrap();
+        assert!(log
+            .entries()
+            .iter()
+            .any(|entry| entry.text == "The Goblin dies of poison."));
+    }
+}
@@ -0,0 +1,479 @@
+use crate::prelude::*;
+
+use super::inventory::apply_potion;
+use super::player_input::{ranged_weapon, visible_enemies};
+
+// How far the player can throw things that aren't made for throwing
+const THROW_RANGE: i32 = 5;
+// How far from where it lands a shattered potion splashes
+const SPLASH_RADIUS: f32 = 1.0;
+
+// Steer the targeting cursor and fire the player's ranged weapon, throw the item
+// they picked from their inventory, or cast the spell they picked. Movement keys move
+// the cursor, the next-target key cycles through visible enemies (nearest first),
+// confirming fires (or throws, or casts) at the cursor and cancelling goes back to normal play.
+// A shot hits the first creature on its line; thrown weapons land where they hit.
+#[system]
+#[allow(clippy::too_many_arguments)]
+#[read_component(Point)]
+#[read_component(Player)]
+#[read_component(Enemy)]
+#[read_component(FieldOfView)]
+#[read_component(Carried)]
+#[read_component(Name)]
+#[read_component(RangedWeapon)]
+#[read_component(Thrown)]
+#[read_component(Inflicts)]
+#[read_component(Potion)]
+#[read_component(Healing)]
+#[read_component(Throwing)]
+#[read_component(Casting)]
+#[write_component(Health)]
+#[write_component(StatusEffects)]
+pub fn targeting(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] key: &Option<VirtualKeyCode>,
+    #[resource] bindings: &KeyBindings,
+    #[resource] map: &Map,
+    #[resource] occupancy: &Occupancy,
+    #[resource] identification: &mut Identification,
+    #[resource] templates: &Templates,
+    #[resource] turn_state: &mut TurnState,
+    #[resource] log: &mut GameLog,
+    #[resource] stats: &mut RunStats,
+) {
+    let cursor = match turn_state {
+        TurnState::Targeting { cursor } => *cursor,
+        _ => return,
+    };
+    let action = match key.and_then(|key| bindings.action(key)) {
+        Some(action) => action,
+        None => return,
+    };
+
+    let (player, player_pos, fov) = match <(Entity, &Point, &FieldOfView)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some((entity, pos, fov)) => (*entity, *pos, fov.clone()),
+        None => return,
+    };
+    let throwing = <(Entity, &Carried)>::query()
+        .filter(component::<Throwing>())
+        .iter(ecs)
+        .find(|(_, carried)| carried.0 == player)
+        .map(|(item, _)| *item);
+    let casting = <&Casting>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+        .and_then(|casting| templates.spell(&casting.0));
+
+    let new_cursor = match action {
+        Action::Cancel => {
+            if let Some(item) = throwing {
+                commands.remove_component::<Throwing>(item);
+            }
+            if casting.is_some() {
+                commands.remove_component::<Casting>(player);
+            }
+            *turn_state = TurnState::AwaitingInput;
+            return;
+        }
+        Action::NextTarget => {
+            // Move to the next enemy after the one under the cursor, wrapping around
+            let enemies = visible_enemies(ecs, player_pos, &fov);
+            let next = enemies
+                .iter()
+                .position(|pos| *pos == cursor)
+                .map_or(0, |i| (i + 1) % enemies.len().max(1));
+            enemies.get(next).copied().unwrap_or(cursor)
+        }
+        Action::Confirm => {
+            let taken = match (casting, throwing) {
+                (Some(spell), _) => cast(
+                    ecs, commands, map, occupancy, log, player, player_pos, &fov, spell, cursor,
+                ),
+                (None, Some(item)) => throw(
+                    ecs,
+                    commands,
+                    map,
+                    occupancy,
+                    identification,
+                    log,
+                    stats,
+                    player,
+                    player_pos,
+                    &fov,
+                    item,
+                    cursor,
+                ),
+                (None, None) => fire(
+                    ecs, commands, map, occupancy, log, player, player_pos, &fov, cursor,
+                ),
+            };
+            if taken {
+                *turn_state = TurnState::PlayerTurn;
+            }
+            return;
+        }
+        _ => match action.delta() {
+            Some(delta) if map.in_bounds(cursor + delta) => cursor + delta,
+            _ => cursor,
+        },
+    };
+
+    *turn_state = TurnState::Targeting { cursor: new_cursor };
+}
+
+// Fire at `target`, if the shot is possible. Returns whether it was taken.
+#[allow(clippy::too_many_arguments)]
+fn fire(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    map: &Map,
+    occupancy: &Occupancy,
+    log: &mut GameLog,
+    player: Entity,
+    player_pos: Point,
+    fov: &FieldOfView,
+    target: Point,
+) -> bool {
+    let weapon_entity = match ranged_weapon(ecs, player) {
+        Some(weapon) => weapon,
+        None => return false,
+    };
+    let weapon = match ecs.entry_ref(weapon_entity) {
+        Ok(weapon) => weapon,
+        Err(_) => return false,
+    };
+    let (range, damage) = match weapon.get_component::<RangedWeapon>() {
+        Ok(ranged) => (ranged.range, ranged.damage),
+        Err(_) => return false,
+    };
+    let name = weapon
+        .get_component::<Name>()
+        .map_or_else(|_| "weapon".to_string(), |name| name.0.clone());
+    let thrown = weapon.get_component::<Thrown>().is_ok();
+    let inflicts = weapon
+        .get_component::<Inflicts>()
+        .ok()
+        .map(|inflicts| inflicts.0);
+
+    let line = match aim(map, log, player_pos, fov, target, range, &name) {
+        Some(line) => line,
+        None => return false,
+    };
+    let (hit_pos, victim) = match first_creature(ecs, occupancy, &line) {
+        Some(hit) => hit,
+        None => {
+            log.add("There is nothing there to shoot at.", GREY);
+            return false;
+        }
+    };
+
+    if thrown {
+        log.add(format!("You throw the {}.", name), WHITE);
+        commands.remove_component::<Carried>(weapon_entity);
+        commands.add_component(weapon_entity, hit_pos);
+    } else {
+        log.add(format!("You fire the {}.", name), WHITE);
+    }
+
+    commands.push((
+        (),
+        WantsToAttack {
+            attacker: player,
+            victim,
+            damage,
+            inflicts,
+        },
+    ));
+    true
+}
+
+// Throw `item` at `target`. It flies until it hits a creature or reaches the target.
+// Weapons hurt whoever they hit, potions shatter and splash everyone close to where
+// they land, and anything else simply drops there. Returns whether it was thrown.
+#[allow(clippy::too_many_arguments)]
+fn throw(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    map: &Map,
+    occupancy: &Occupancy,
+    identification: &mut Identification,
+    log: &mut GameLog,
+    stats: &mut RunStats,
+    player: Entity,
+    player_pos: Point,
+    fov: &FieldOfView,
+    item: Entity,
+    target: Point,
+) -> bool {
+    let (name, ranged, potion, inflicts) = match ecs.entry_ref(item) {
+        Ok(item) => (
+            item.get_component::<Name>()
+                .map_or_else(|_| "item".to_string(), |name| name.0.clone()),
+            item.get_component::<RangedWeapon>().ok().copied(),
+            item.get_component::<Potion>().is_ok(),
+            item.get_component::<Inflicts>()
+                .ok()
+                .map(|inflicts| inflicts.0),
+        ),
+        Err(_) => return false,
+    };
+    let display_name = identification.display_name(&name);
+    let range = ranged.map_or(THROW_RANGE, |ranged| ranged.range);
+
+    let line = match aim(map, log, player_pos, fov, target, range, &display_name) {
+        Some(line) => line,
+        None => return false,
+    };
+    let hit = first_creature(ecs, occupancy, &line);
+    let landing = hit.map_or(target, |(pos, _)| pos);
+
+    log.add(format!("You throw the {}.", display_name), WHITE);
+    commands.remove_component::<Throwing>(item);
+    commands.remove_component::<Carried>(item);
+
+    if potion {
+        log.add(format!("The {} shatters!", display_name), WHITE);
+        let splashed: Vec<Entity> = <(Entity, &Point)>::query()
+            .filter(component::<Health>())
+            .iter(ecs)
+            .filter(|(_, pos)| DistanceAlg::Chebyshev.distance2d(landing, **pos) <= SPLASH_RADIUS)
+            .map(|(entity, _)| *entity)
+            .collect();
+        splashed
+            .iter()
+            .for_each(|victim| apply_potion(ecs, item, *victim, log));
+        // Seeing what the potion did to someone gives it away
+        if !splashed.is_empty() && identification.identify(&name) {
+            log.add(format!("It was {}.", a_or_an(&name)), YELLOW);
+        }
+        commands.remove(item);
+        stats.items_used += 1;
+        return true;
+    }
+
+    commands.add_component(item, landing);
+    if let (Some(ranged), Some((_, victim))) = (ranged, hit) {
+        commands.push((
+            (),
+            WantsToAttack {
+                attacker: player,
+                victim,
+                damage: ranged.damage,
+                inflicts,
+            },
+        ));
+    }
+    true
+}
+
+// Cast `spell` at `target`, if it can reach. Blinking needs an empty spot to land on,
+// and a bolt needs someone to hit. Returns whether it was cast.
+#[allow(clippy::too_many_arguments)]
+fn cast(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    map: &Map,
+    occupancy: &Occupancy,
+    log: &mut GameLog,
+    player: Entity,
+    player_pos: Point,
+    fov: &FieldOfView,
+    spell: &SpellTemplate,
+    target: Point,
+) -> bool {
+    let range = spell.range.unwrap_or(0);
+    let line = match aim(map, log, player_pos, fov, target, range, &spell.name) {
+        Some(line) => line,
+        None => return false,
+    };
+
+    if spell.blink && (!map.can_enter_tile(target) || occupancy.is_occupied(target)) {
+        log.add("There is no room to land there.", GREY);
+        return false;
+    }
+    if spell.radius.is_none()
+        && spell.damage.is_some()
+        && first_creature(ecs, occupancy, &line).is_none()
+    {
+        log.add("There is nothing there to shoot at.", GREY);
+        return false;
+    }
+
+    commands.remove_component::<Casting>(player);
+    commands.push((
+        (),
+        WantsToCast {
+            caster: player,
+            spell: spell.name.clone(),
+            target,
+        },
+    ));
+    true
+}
+
+// Check that the player at `from` can hit `target` with `name`, which reaches `range`
+// tiles, and return the line it would travel. Tells the player why not, otherwise.
+fn aim(
+    map: &Map,
+    log: &mut GameLog,
+    from: Point,
+    fov: &FieldOfView,
+    target: Point,
+    range: i32,
+    name: &str,
+) -> Option<Vec<Point>> {
+    if !fov.visible_tiles.contains(&target) {
+        log.add("You can't see that spot.", GREY);
+        return None;
+    }
+    if DistanceAlg::Pythagoras.distance2d(from, target) > range as f32 {
+        log.add(format!("That is out of range of the {}.", name), GREY);
+        return None;
+    }
+    let line = map.line_of_fire(from, target);
+    if line.is_none() {
+        log.add("Something is in the way.", GREY);
+    }
+    line
+}
+
+// The first creature along `line`, and where it stands.
+pub fn first_creature(
+    ecs: &SubWorld,
+    occupancy: &Occupancy,
+    line: &[Point],
+) -> Option<(Point, Entity)> {
+    line.iter().find_map(|pos| {
+        occupancy
+            .entities_at(*pos)
+            .iter()
+            .find(|occupant| {
+                ecs.entry_ref(**occupant)
+                    .is_ok_and(|entry| entry.get_component::<Health>().is_ok())
+            })
+            .map(|victim| (*pos, *victim))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An open map with the player at (2, 5) seeing all of it, carrying a weapon with a
+    // range of 6, and an enemy standing at each of `enemies`.
+    fn setup(thrown: bool, enemies: &[Point]) -> (World, Resources, Entity, Vec<Entity>) {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        let map = Map::new(20, 10);
+        let mut fov = FieldOfView::new(20);
+        fov.visible_tiles = (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| Point::new(x, y)))
+            .collect();
+        resources.insert(map);
+        resources.insert(Occupancy::default());
+        resources.insert(GameLog::default());
+        resources.insert(KeyBindings::default());
+        resources.insert(Identification::new(
+            &mut RandomNumberGenerator::seeded(1),
+            &[],
+        ));
+        resources.insert(Templates::load());
+        resources.insert(RunStats::default());
+
+        let player = ecs.push((
+            Player { map_level: 0 },
+            Point::new(2, 5),
+            fov,
+            Health {
+                current: 10,
+                max: 10,
+            },
+        ));
+        let weapon = ecs.push((
+            Name("Dagger".to_string()),
+            RangedWeapon {
+                range: 6,
+                damage: 2,
+            },
+            Carried(player),
+        ));
+        if thrown {
+            ecs.entry(weapon).unwrap().add_component(Thrown);
+        }
+        let enemies = enemies
+            .iter()
+            .map(|pos| ecs.push((Enemy, *pos, Health { current: 3, max: 3 })))
+            .collect();
+        (ecs, resources, weapon, enemies)
+    }
+
+    // Fire at `cursor` and return the turn state that follows.
+    fn fire_at(ecs: &mut World, resources: &mut Resources, cursor: Point) -> TurnState {
+        resources.insert(TurnState::Targeting { cursor });
+        resources.insert(Some(VirtualKeyCode::Return));
+
+        Schedule::builder()
+            .add_system(super::super::occupancy::occupancy_system())
+            .flush()
+            .add_system(targeting_system())
+            .build()
+            .execute(ecs, resources);
+
+        let turn_state = *resources.get::<TurnState>().unwrap();
+        turn_state
+    }
+
+    fn victims(ecs: &World) -> Vec<Entity> {
+        <&WantsToAttack>::query()
+            .iter(ecs)
+            .map(|attack| attack.victim)
+            .collect()
+    }
+
+    #[test]
+    fn a_shot_hits_the_first_creature_on_its_line() {
+        let (mut ecs, mut resources, _, enemies) =
+            setup(false, &[Point::new(4, 5), Point::new(6, 5)]);
+
+        let turn_state = fire_at(&mut ecs, &mut resources, Point::new(6, 5));
+
+        assert_eq!(turn_state, TurnState::PlayerTurn);
+        assert_eq!(victims(&ecs), vec![enemies[0]]);
+    }
+
+    #[test]
+    fn a_thrown_weapon_lands_where_it_hit() {
+        let (mut ecs, mut resources, weapon, _) = setup(true, &[Point::new(5, 5)]);
+
+        fire_at(&mut ecs, &mut resources, Point::new(5, 5));
+
+        let weapon = ecs.entry(weapon).unwrap();
+        assert_eq!(weapon.get_component::<Point>(), Ok(&Point::new(5, 5)));
+        assert!(weapon.get_component::<Carried>().is_err());
+    }
+
+    #[test]
+    fn a_target_out_of_range_or_behind_a_wall_is_not_shot() {
+        let (mut ecs, mut resources, _, _) = setup(false, &[Point::new(12, 5), Point::new(2, 8)]);
+        {
+            let mut map = resources.get_mut::<Map>().unwrap();
+            let idx = map.map_index(2, 7);
+            map.tiles[idx] = TileType::Wall;
+        }
+
+        for cursor in [Point::new(12, 5), Point::new(2, 8)] {
+            let turn_state = fire_at(&mut ecs, &mut resources, cursor);
+
+            // The player keeps aiming rather than losing their turn
+            assert_eq!(turn_state, TurnState::Targeting { cursor });
+        }
+        assert!(victims(&ecs).is_empty());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::prelude::*;
+
+// While targeting, outline the tile under the cursor and dot the line a shot would take.
+// The line is cyan when the shot is clear and red when a wall is in the way.
+// It is drawn on the HUD console, whose cells are a quarter of a map tile across.
+#[system]
+#[read_component(Point)]
+#[read_component(Player)]
+#[read_component(Throwing)]
+#[read_component(Casting)]
+pub fn targeting_render(
+    ecs: &SubWorld,
+    #[resource] turn_state: &TurnState,
+    #[resource] map: &Map,
+    #[resource] camera: &Camera,
+) {
+    let cursor = match turn_state {
+        TurnState::Targeting { cursor } => *cursor,
+        _ => return,
+    };
+    let player_pos = match <&Point>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(pos) => *pos,
+        None => return,
+    };
+
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(2);
+    let offset = Point::new(camera.left_x, camera.top_y);
+    let scale = HUD_WIDTH / DISPLAY_WIDTH;
+
+    let color = if map.line_of_fire(player_pos, cursor).is_some() {
+        CYAN
+    } else {
+        RED
+    };
+    line2d(LineAlg::Bresenham, player_pos, cursor)
+        .into_iter()
+        .filter(|pos| *pos != player_pos && *pos != cursor && camera.in_view(*pos))
+        .for_each(|pos| {
+            let screen = (pos - offset) * scale;
+            draw_batch.set(
+                screen + Point::new(scale / 2, scale / 2),
+                ColorPair::new(color, BLACK),
+                to_cp437('*'),
+            );
+        });
+
+    let screen = (cursor - offset) * scale;
+    draw_batch.draw_hollow_box(
+        Rect::with_size(screen.x, screen.y, scale - 1, scale - 1),
+        ColorPair::new(color, BLACK),
+    );
+    let verb = if <&Throwing>::query().iter(ecs).next().is_some() {
+        "throw"
+    } else if <&Casting>::query().iter(ecs).next().is_some() {
+        "cast"
+    } else {
+        "fire"
+    };
+    draw_batch.print_color_centered(
+        2,
+        format!("Targeting: Tab next target, Enter {}, Esc cancel", verb),
+        ColorPair::new(YELLOW, BLACK),
+    );
+
+    // Drawn over the HUD
+    draw_batch.submit(10100).expect("Batch error");
+}
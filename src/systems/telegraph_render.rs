@@ -0,0 +1,28 @@
+use crate::prelude::*;
+
+// Outline the tiles the boss is about to slam, so the player has a turn to get clear.
+// Like the targeting cursor, it is drawn on the HUD console, whose cells are a quarter
+// of a map tile across.
+#[system]
+#[read_component(Boss)]
+pub fn telegraph_render(ecs: &SubWorld, #[resource] camera: &Camera) {
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(2);
+    let offset = Point::new(camera.left_x, camera.top_y);
+    let scale = HUD_WIDTH / DISPLAY_WIDTH;
+
+    <&Boss>::query()
+        .iter(ecs)
+        .flat_map(|boss| boss.telegraphed.iter())
+        .filter(|pos| camera.in_view(**pos))
+        .for_each(|pos| {
+            let screen = (*pos - offset) * scale;
+            draw_batch.draw_hollow_box(
+                Rect::with_size(screen.x, screen.y, scale - 1, scale - 1),
+                ColorPair::new(RED, BLACK),
+            );
+        });
+
+    // Drawn over the HUD
+    draw_batch.submit(10100).expect("Batch error");
+}
@@ -0,0 +1,60 @@
+use crate::prelude::*;
+
+// Name whatever is under the mouse, as long as the player can see it.
+// Hidden traps stay hidden, and unidentified items go by their appearance. The mouse position is in map console cells,
+// so it is scaled up to the HUD console to draw the text.
+#[system]
+#[read_component(Point)]
+#[read_component(Name)]
+#[read_component(Health)]
+#[read_component(Player)]
+#[read_component(FieldOfView)]
+#[read_component(Hidden)]
+pub fn tooltips(
+    ecs: &SubWorld,
+    #[resource] mouse_pos: &Point,
+    #[resource] camera: &Camera,
+    #[resource] identification: &Identification,
+) {
+    let offset = Point::new(camera.left_x, camera.top_y);
+    let map_pos = *mouse_pos + offset;
+
+    let fov = match <&FieldOfView>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(fov) => fov,
+        None => return,
+    };
+    if !fov.visible_tiles.contains(&map_pos) {
+        return;
+    }
+
+    let names: Vec<String> = <(&Point, &Name, Option<&Health>)>::query()
+        .filter(!component::<Hidden>())
+        .iter(ecs)
+        .filter(|(pos, _, _)| **pos == map_pos)
+        .map(|(_, name, health)| match health {
+            Some(health) => format!("{} {}/{}", name.0, health.current, health.max),
+            None => identification.display_name(&name.0),
+        })
+        .collect();
+    if names.is_empty() {
+        return;
+    }
+
+    let mut draw_batch = DrawBatch::new();
+    draw_batch.target(2);
+    let scale = HUD_WIDTH / DISPLAY_WIDTH;
+    let text = names.join(", ");
+    // Keep the tooltip on screen near the right edge
+    let x = (*mouse_pos * scale)
+        .x
+        .min(HUD_WIDTH - text.len() as i32)
+        .max(0);
+    let y = (*mouse_pos * scale).y - 1;
+    draw_batch.print_color(Point::new(x, y.max(0)), &text, ColorPair::new(WHITE, BLACK));
+
+    draw_batch.submit(10100).expect("Batch error");
+}
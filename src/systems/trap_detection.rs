@@ -0,0 +1,38 @@
+use crate::prelude::*;
+
+// The chance, one in this many, of noticing a hidden trap next to the player each turn
+const NOTICE_CHANCE: i32 = 4;
+
+// Give the player a chance to notice hidden traps on the tiles around them.
+#[system]
+#[read_component(Point)]
+#[read_component(Player)]
+#[read_component(Trap)]
+#[read_component(Hidden)]
+#[read_component(Name)]
+pub fn trap_detection(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] log: &mut GameLog,
+) {
+    let player_pos = match <&Point>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .next()
+    {
+        Some(pos) => *pos,
+        None => return,
+    };
+
+    <(Entity, &Point, &Name)>::query()
+        .filter(component::<Trap>() & component::<Hidden>())
+        .iter(ecs)
+        .filter(|(_, pos, _)| DistanceAlg::Chebyshev.distance2d(player_pos, **pos) <= 1.0)
+        .for_each(|(trap, _, name)| {
+            if rng.range(0, NOTICE_CHANCE) == 0 {
+                commands.remove_component::<Hidden>(*trap);
+                log.add(format!("You notice {}.", a_or_an(&name.0)), YELLOW);
+            }
+        });
+}
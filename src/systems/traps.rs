@@ -0,0 +1,128 @@
+use crate::prelude::*;
+
+// How many turns monsters woken by an alarm keep searching for its source
+const ALARM_TURNS: i32 = 10;
+// How often a teleport trap tries random tiles before giving up on finding a free one
+const TELEPORT_ATTEMPTS: usize = 1000;
+
+// Spring the traps that were stepped on this turn. A sprung trap is no longer hidden.
+// Spike traps attack their victim (the attack is resolved by `combat`), teleport traps
+// move them to a random free tile and alarms send every monster to search the trap.
+// Only what the player can see is written to the log.
+#[system]
+#[read_component(TriggerTrap)]
+#[read_component(Trap)]
+#[read_component(Name)]
+#[read_component(Player)]
+#[write_component(Point)]
+#[write_component(FieldOfView)]
+#[write_component(MonsterAi)]
+pub fn traps(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &Map,
+    #[resource] occupancy: &mut Occupancy,
+    #[resource] camera: &mut Camera,
+    #[resource] rng: &mut RandomNumberGenerator,
+    #[resource] log: &mut GameLog,
+) {
+    let triggers: Vec<(Entity, TriggerTrap)> = <(Entity, &TriggerTrap)>::query()
+        .iter(ecs)
+        .map(|(message, trigger)| (*message, *trigger))
+        .collect();
+
+    triggers.iter().for_each(|(message, trigger)| {
+        commands.remove(*message);
+
+        let (kind, trap_pos, trap_name) = match ecs.entry_ref(trigger.trap) {
+            Ok(trap) => match (
+                trap.get_component::<Trap>(),
+                trap.get_component::<Point>(),
+                trap.get_component::<Name>(),
+            ) {
+                (Ok(trap), Ok(pos), Ok(name)) => (trap.kind, *pos, name.0.clone()),
+                _ => return,
+            },
+            Err(_) => return,
+        };
+        commands.remove_component::<Hidden>(trigger.trap);
+
+        let victim_is_player = ecs
+            .entry_ref(trigger.victim)
+            .is_ok_and(|victim| victim.get_component::<Player>().is_ok());
+        let victim_name = match ecs
+            .entry_ref(trigger.victim)
+            .ok()
+            .and_then(|victim| victim.get_component::<Name>().ok().cloned())
+        {
+            _ if victim_is_player => "You".to_string(),
+            Some(name) => format!("The {}", name.0),
+            None => "Something".to_string(),
+        };
+        let seen = victim_is_player
+            || <&FieldOfView>::query()
+                .filter(component::<Player>())
+                .iter(ecs)
+                .any(|fov| fov.visible_tiles.contains(&trap_pos));
+        if seen {
+            log.add(
+                format!(
+                    "{} {} off {}!",
+                    victim_name,
+                    if victim_is_player { "set" } else { "sets" },
+                    a_or_an(&trap_name)
+                ),
+                ORANGE,
+            );
+        }
+
+        match kind {
+            TrapKind::Spike => {
+                commands.push((
+                    (),
+                    WantsToAttack {
+                        attacker: trigger.trap,
+                        victim: trigger.victim,
+                        damage: 2,
+                        inflicts: None,
+                    },
+                ));
+            }
+            TrapKind::Teleport => {
+                if let Some(destination) = free_tile(map, occupancy, rng) {
+                    if let Ok(mut victim) = ecs.entry_mut(trigger.victim) {
+                        if let Ok(pos) = victim.get_component_mut::<Point>() {
+                            occupancy.remove(*pos, trigger.victim);
+                            occupancy.add(destination, trigger.victim);
+                            *pos = destination;
+                        }
+                        if let Ok(fov) = victim.get_component_mut::<FieldOfView>() {
+                            fov.is_dirty = true;
+                        }
+                    }
+                    if victim_is_player {
+                        camera.on_player_move(destination, map);
+                    }
+                }
+            }
+            TrapKind::Alarm => {
+                <&mut MonsterAi>::query()
+                    .iter_mut(ecs)
+                    .filter(|ai| !matches!(ai.state, AiState::Hunt { .. } | AiState::Flee))
+                    .for_each(|ai| {
+                        ai.state = AiState::Search {
+                            last_known: trap_pos,
+                            turns_left: ALARM_TURNS,
+                        }
+                    });
+            }
+        }
+    });
+}
+
+// A random floor tile with nobody standing on it.
+fn free_tile(map: &Map, occupancy: &Occupancy, rng: &mut RandomNumberGenerator) -> Option<Point> {
+    (0..TELEPORT_ATTEMPTS)
+        .map(|_| Point::new(rng.range(0, map.width), rng.range(0, map.height)))
+        .find(|pos| map.can_enter_tile(*pos) && !occupancy.is_occupied(*pos))
+}
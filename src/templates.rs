@@ -0,0 +1,211 @@
+use crate::prelude::*;
+
+// What each kind of monster is like, and how deep in the dungeon it turns up.
+// The templates live in `resources/templates.json`, which is built into the game.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonsterTemplate {
+    pub name: String,
+    pub glyph: char,
+    pub color: (u8, u8, u8),
+    pub health: i32,
+    // Damage dealt by a melee attack
+    pub damage: i32,
+    // Experience awarded to the player for the kill
+    pub xp: i32,
+    // The shallowest depth the monster appears at
+    pub min_depth: i32,
+    // How common the monster is compared to the others at the same depth
+    pub frequency: i32,
+    #[serde(default)]
+    pub ranged: Option<RangedWeapon>,
+    #[serde(default)]
+    pub inflicts: Option<StatusEffect>,
+    // The most gold the monster may carry, and drop when it dies
+    #[serde(default)]
+    pub gold: i32,
+    // Bosses aren't spawned at random; one waits at the bottom of the dungeon
+    #[serde(default)]
+    pub boss: bool,
+    // The monster a boss calls to its aid
+    #[serde(default)]
+    pub summons: Option<String>,
+    // The light given off by monsters that glow
+    #[serde(default)]
+    pub light: Option<LightSource>,
+}
+
+// What each kind of item is like, what it is worth and how often it is found.
+// Only the fields that apply to an item need to be given.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemTemplate {
+    pub name: String,
+    pub glyph: char,
+    pub color: (u8, u8, u8),
+    // What a shop asks for the item; it pays half that
+    pub price: i32,
+    // How common the item is compared to the others
+    pub frequency: i32,
+    #[serde(default)]
+    pub ranged: Option<RangedWeapon>,
+    // Whether a ranged weapon is thrown away when used
+    #[serde(default)]
+    pub thrown: bool,
+    #[serde(default)]
+    pub inflicts: Option<StatusEffect>,
+    #[serde(default)]
+    pub potion: bool,
+    #[serde(default)]
+    pub healing: Option<i32>,
+    #[serde(default)]
+    pub food: Option<i32>,
+    #[serde(default)]
+    pub slot: Option<EquipmentSlot>,
+    #[serde(default)]
+    pub defense: Option<i32>,
+    // The spell a book teaches whoever reads it
+    #[serde(default)]
+    pub teaches: Option<String>,
+}
+
+// What the player can start out as: how tough they are, what they carry,
+// and what each class can do that the others can't.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClassTemplate {
+    pub name: String,
+    // A line shown on the class selection screen
+    pub description: String,
+    pub health: i32,
+    pub damage: i32,
+    #[serde(default)]
+    pub gold: i32,
+    // The starting kit, by item template name. Armour in it starts out worn.
+    #[serde(default)]
+    pub items: Vec<String>,
+    // How far the player can see, if further than usual
+    #[serde(default)]
+    pub sight: Option<i32>,
+    // Whether the player knows every kind of potion from the start
+    #[serde(default)]
+    pub knows_potions: bool,
+    #[serde(default)]
+    pub mana: i32,
+    // The spells the player knows from the start
+    #[serde(default)]
+    pub spells: Vec<String>,
+}
+
+// What a spell costs and does. Spells with a `range` are aimed with the targeting
+// cursor; the others are cast on the caster. Only the fields that apply need to be given.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpellTemplate {
+    pub name: String,
+    pub mana: i32,
+    #[serde(default)]
+    pub range: Option<i32>,
+    // Damage dealt to the first creature in the spell's path, or to every creature
+    // within `radius` of where it strikes
+    #[serde(default)]
+    pub damage: Option<i32>,
+    #[serde(default)]
+    pub radius: Option<i32>,
+    #[serde(default)]
+    pub inflicts: Option<StatusEffect>,
+    #[serde(default)]
+    pub healing: Option<i32>,
+    // A status effect the spell rids the caster of
+    #[serde(default)]
+    pub cures: Option<StatusKind>,
+    // Whether the spell moves the caster to the target
+    #[serde(default)]
+    pub blink: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Templates {
+    pub monsters: Vec<MonsterTemplate>,
+    pub items: Vec<ItemTemplate>,
+    pub classes: Vec<ClassTemplate>,
+    pub spells: Vec<SpellTemplate>,
+}
+
+impl Templates {
+    pub fn load() -> Self {
+        serde_json::from_str(include_str!("../resources/templates.json"))
+            .expect("Invalid templates.json")
+    }
+
+    // Pick a monster that can appear at `depth`, weighted by frequency.
+    pub fn random_monster(&self, rng: &mut RandomNumberGenerator, depth: i32) -> &MonsterTemplate {
+        let candidates: Vec<&MonsterTemplate> = self
+            .monsters
+            .iter()
+            .filter(|monster| monster.min_depth <= depth && !monster.boss)
+            .collect();
+        let total: i32 = candidates.iter().map(|monster| monster.frequency).sum();
+
+        let mut roll = rng.range(0, total);
+        for monster in candidates.iter() {
+            if roll < monster.frequency {
+                return monster;
+            }
+            roll -= monster.frequency;
+        }
+        candidates[0]
+    }
+
+    // The boss waiting at the bottom of the dungeon.
+    pub fn boss(&self) -> Option<&MonsterTemplate> {
+        self.monsters.iter().find(|monster| monster.boss)
+    }
+
+    pub fn item(&self, name: &str) -> Option<&ItemTemplate> {
+        self.items.iter().find(|item| item.name == name)
+    }
+
+    // The class called `name`, or the first class if there is none by that name.
+    pub fn class(&self, name: &str) -> &ClassTemplate {
+        self.classes
+            .iter()
+            .find(|class| class.name == name)
+            .unwrap_or(&self.classes[0])
+    }
+
+    pub fn spell(&self, name: &str) -> Option<&SpellTemplate> {
+        self.spells.iter().find(|spell| spell.name == name)
+    }
+
+    pub fn monster(&self, name: &str) -> Option<&MonsterTemplate> {
+        self.monsters.iter().find(|monster| monster.name == name)
+    }
+
+    // Pick any item, weighted by frequency.
+    pub fn random_item(&self, rng: &mut RandomNumberGenerator) -> &ItemTemplate {
+        let total: i32 = self.items.iter().map(|item| item.frequency).sum();
+
+        let mut roll = rng.range(0, total);
+        for item in self.items.iter() {
+            if roll < item.frequency {
+                return item;
+            }
+            roll -= item.frequency;
+        }
+        &self.items[0]
+    }
+
+    // The names of all the kinds of potion, which need identifying.
+    pub fn potion_names(&self) -> Vec<&str> {
+        self.items
+            .iter()
+            .filter(|item| item.potion)
+            .map(|item| item.name.as_str())
+            .collect()
+    }
+
+    // What a shop asks for the item called `name`.
+    pub fn price(&self, name: &str) -> i32 {
+        self.items
+            .iter()
+            .find(|item| item.name == name)
+            .map_or(0, |item| item.price)
+    }
+}
@@ -0,0 +1,75 @@
+use crate::prelude::*;
+
+// The game alternates between waiting for the player, resolving the player's
+// action and letting the monsters act. Each state runs its own schedule.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TurnState {
+    AwaitingInput,
+    // The player is picking a target for a ranged attack, marked by the cursor
+    Targeting { cursor: Point },
+    PlayerTurn,
+    MonsterTurn,
+    // Hasted monsters take their extra action
+    HastedMonsterTurn,
+    // The player is looking at their equipment, to put things on or take them off
+    Equipment,
+    // The player is picking an item from their pack to drink, eat, read, drop, throw or sell,
+    // or from a shop's stock to buy
+    Inventory { mode: InventoryMode },
+    // The player is choosing a spell to cast
+    Spells,
+    // The player has gained a level and is choosing how to improve
+    LevelUp,
+    // The player has taken the stairs and the next level needs building
+    NextLevel,
+    GameOver,
+    // The player has the Amulet in hand and has won
+    Victory,
+}
+
+impl TurnState {
+    // Whether the run has ended, one way or the other.
+    pub fn is_over(&self) -> bool {
+        matches!(self, TurnState::GameOver | TurnState::Victory)
+    }
+
+    // Whether the game is waiting for a key press from the player.
+    pub fn awaits_input(&self) -> bool {
+        matches!(
+            self,
+            TurnState::AwaitingInput
+                | TurnState::Targeting { .. }
+                | TurnState::Equipment
+                | TurnState::Inventory { .. }
+                | TurnState::Spells
+                | TurnState::LevelUp
+        )
+    }
+}
+
+// What picking an item on the inventory screen does with it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InventoryMode {
+    Quaff,
+    Eat,
+    Read,
+    Drop,
+    Throw,
+    // Trading with a shopkeeper: buying from its stock, or selling from the pack
+    Buy,
+    Sell,
+}
+
+impl InventoryMode {
+    pub fn title(self) -> &'static str {
+        match self {
+            InventoryMode::Quaff => "Drink which potion?",
+            InventoryMode::Eat => "Eat what?",
+            InventoryMode::Read => "Read which book?",
+            InventoryMode::Drop => "Drop which item?",
+            InventoryMode::Throw => "Throw which item?",
+            InventoryMode::Buy => "Buy what?",
+            InventoryMode::Sell => "Sell what?",
+        }
+    }
+}